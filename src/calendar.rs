@@ -0,0 +1,234 @@
+use crate::date::week_start;
+use crate::{Error, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::path::Path;
+
+/// How the dining halls operate on a day
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum Service {
+    #[default]
+    Regular,
+    /// Fewer halls or meals are open, as during finals week and most breaks
+    Reduced,
+    /// No menus are served
+    Closed,
+}
+
+impl Service {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Regular => "Regular".into(),
+            Self::Reduced => "Reduced".into(),
+            Self::Closed => "Closed".into(),
+        }
+    }
+}
+
+/// An academic quarter, from its first day of instruction through its last day of finals
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Quarter {
+    pub name: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// Number of the week the quarter starts in. Fall quarter starts on a Thursday of week 0.
+    pub first_week: u32,
+    /// First day of final exams
+    pub finals: Option<NaiveDate>,
+}
+
+impl Quarter {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+
+    /// Number of the Monday to Sunday week of the quarter the date falls in
+    pub fn week(&self, date: NaiveDate) -> u32 {
+        let weeks = (week_start(date) - week_start(self.start)).num_weeks();
+        (self.first_week as i64 + weeks).max(0) as u32
+    }
+}
+
+/// Days between or within quarters when the halls change their schedule
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Break {
+    pub name: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub service: Service,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Holiday {
+    pub name: String,
+    pub date: NaiveDate,
+    pub service: Service,
+}
+
+/// Where a date falls in the academic calendar and how the halls operate on it
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct CalendarDay {
+    pub quarter: Option<String>,
+    pub week: Option<u32>,
+    pub service: Service,
+    /// The holiday, break or exams changing service on the day
+    pub note: Option<String>,
+}
+
+/// Quarters, breaks and holidays of the academic year, loaded from a JSON data file such as
+/// `data/academic_calendar.json`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct AcademicCalendar {
+    #[serde(default)]
+    pub quarters: Vec<Quarter>,
+    #[serde(default)]
+    pub breaks: Vec<Break>,
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+}
+
+impl AcademicCalendar {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|e| Error::io(path, e))?;
+        serde_json::from_reader(file).map_err(|e| Error::serialization(path, e))
+    }
+
+    pub fn quarter(&self, date: NaiveDate) -> Option<&Quarter> {
+        self.quarters.iter().find(|q| q.contains(date))
+    }
+
+    /// Look up a date. Holidays take precedence over breaks, which take precedence over finals,
+    /// and dates the calendar doesn't cover get regular service.
+    pub fn day(&self, date: NaiveDate) -> CalendarDay {
+        let mut day = CalendarDay::default();
+        if let Some(quarter) = self.quarter(date) {
+            day.quarter = Some(quarter.name.clone());
+            day.week = Some(quarter.week(date));
+            if matches!(quarter.finals, Some(finals) if finals <= date) {
+                day.service = Service::Reduced;
+                day.note = Some("Finals".into());
+            }
+        }
+        if let Some(b) = self
+            .breaks
+            .iter()
+            .find(|b| b.start <= date && date <= b.end)
+        {
+            day.service = b.service;
+            day.note = Some(b.name.clone());
+        }
+        if let Some(holiday) = self.holidays.iter().find(|h| h.date == date) {
+            day.service = holiday.service;
+            day.note = Some(holiday.name.clone());
+        }
+        day
+    }
+
+    /// Drops the dates on which the dining halls are closed
+    pub fn service_dates(&self, dates: Vec<NaiveDate>) -> Vec<NaiveDate> {
+        dates
+            .into_iter()
+            .filter(|date| self.day(*date).service != Service::Closed)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = r#"
+{
+  "quarters": [
+    { "name": "Fall 2021", "start": "2021-09-23", "end": "2021-12-10", "first_week": 0, "finals": "2021-12-04" },
+    { "name": "Winter 2022", "start": "2022-01-03", "end": "2022-03-18", "first_week": 1, "finals": "2022-03-12" }
+  ],
+  "breaks": [
+    { "name": "Winter Break", "start": "2021-12-11", "end": "2022-01-02", "service": "Reduced" }
+  ],
+  "holidays": [
+    { "name": "Thanksgiving", "date": "2021-11-25", "service": "Closed" },
+    { "name": "Christmas Day", "date": "2021-12-25", "service": "Closed" }
+  ]
+}
+    "#;
+
+    fn calendar() -> AcademicCalendar {
+        serde_json::from_str(CALENDAR).unwrap()
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> CalendarDay {
+        calendar().day(NaiveDate::from_ymd(year, month, day))
+    }
+
+    #[test]
+    fn test_quarter_weeks() {
+        // Fall quarter starts on the Thursday of week 0
+        assert_eq!(day(2021, 9, 23).week, Some(0));
+        assert_eq!(day(2021, 9, 26).week, Some(0));
+        assert_eq!(day(2021, 9, 27).week, Some(1));
+        assert_eq!(day(2021, 10, 8).quarter.as_deref(), Some("Fall 2021"));
+        assert_eq!(day(2021, 10, 8).week, Some(2));
+        assert_eq!(day(2022, 1, 3).week, Some(1));
+        assert_eq!(day(2021, 12, 20).week, None);
+    }
+
+    #[test]
+    fn test_service() {
+        assert_eq!(day(2021, 10, 8).service, Service::Regular);
+        assert_eq!(
+            day(2021, 12, 7),
+            CalendarDay {
+                quarter: Some("Fall 2021".into()),
+                week: Some(11),
+                service: Service::Reduced,
+                note: Some("Finals".into()),
+            }
+        );
+        assert_eq!(
+            day(2021, 11, 25),
+            CalendarDay {
+                quarter: Some("Fall 2021".into()),
+                week: Some(9),
+                service: Service::Closed,
+                note: Some("Thanksgiving".into()),
+            }
+        );
+        assert_eq!(day(2021, 12, 25).service, Service::Closed);
+        assert_eq!(day(2021, 12, 26).note.as_deref(), Some("Winter Break"));
+        // Summer is not covered by the calendar
+        assert_eq!(day(2022, 7, 1), CalendarDay::default());
+    }
+
+    #[test]
+    fn test_service_dates() {
+        let dates = crate::date::date_range(
+            NaiveDate::from_ymd(2021, 11, 24),
+            NaiveDate::from_ymd(2021, 11, 26),
+        );
+        assert_eq!(
+            calendar().service_dates(dates),
+            vec![
+                NaiveDate::from_ymd(2021, 11, 24),
+                NaiveDate::from_ymd(2021, 11, 26)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calendar.json");
+        std::fs::write(&path, CALENDAR).unwrap();
+        let loaded = AcademicCalendar::load(&path);
+        std::fs::write(&path, "{\"quarters\": {}}").unwrap();
+        let malformed = AcademicCalendar::load(&path);
+
+        assert_eq!(loaded.unwrap(), calendar());
+        assert!(matches!(malformed, Err(Error::Serialization { .. })));
+    }
+}
@@ -1,44 +1,255 @@
-use chrono::{DateTime, Datelike, Duration, FixedOffset, TimeZone, Utc};
+use crate::{Error, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// Most dates downloaded in one run, a year including a leap day.
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+/// Time zone of the dining halls. Menus are published per day in local time, which switches
+/// between PST and PDT.
+pub const LOS_ANGELES: Tz = chrono_tz::America::Los_Angeles;
+
+/// Returns the current date in Los Angeles.
+pub fn today() -> NaiveDate {
+    date_at(Utc::now())
+}
+
+/// Returns the date in Los Angeles at an instant.
+pub fn date_at(instant: DateTime<Utc>) -> NaiveDate {
+    instant.with_timezone(&LOS_ANGELES).date().naive_local()
+}
+
+/// Parses a date in YYYY-MM-DD format, rejecting dates that don't exist.
+pub fn parse_date(date: &str) -> Result<NaiveDate> {
+    let invalid = || Error::InvalidDate { date: date.into() };
+    // chrono accepts unpadded fields, which the site doesn't
+    if date.len() != 10 {
+        return Err(invalid());
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| invalid())
+}
+
+/// Parses a date or a relative date expression into the first and last date it covers.
+///
+/// Besides YYYY-MM-DD dates, `today`, `tomorrow`, `next-<weekday>` (the first such day after
+/// today) and `this-week` (Monday through Sunday) are understood, relative to `today`.
+pub fn parse_date_range(expr: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate)> {
+    let single = |date| Ok((date, date));
+    match expr.to_ascii_lowercase().as_str() {
+        "today" => single(today),
+        "tomorrow" => single(today + Duration::days(1)),
+        "this-week" => {
+            let monday = week_start(today);
+            Ok((monday, monday + Duration::days(6)))
+        }
+        relative => match relative
+            .strip_prefix("next-")
+            .and_then(|day| day.parse::<Weekday>().ok())
+        {
+            Some(weekday) => single(next_weekday(today, weekday)),
+            None => parse_date(expr).and_then(single),
+        },
+    }
+}
+
+/// Returns the Monday of the week the date falls in.
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Returns the first date after `date` that falls on the weekday.
+fn next_weekday(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (weekday.num_days_from_monday() + 7 - date.weekday().num_days_from_monday()) % 7;
+    date + Duration::days(if days == 0 { 7 } else { days as i64 })
+}
+
+/// Returns every date from `from` through `to`, or none when `to` comes first.
+pub fn date_range(from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+    from.iter_days().take_while(|date| *date <= to).collect()
+}
+
+/// Returns `days` consecutive dates starting at `from`, between 1 and `MAX_RANGE_DAYS` of them.
+pub fn days_from(from: NaiveDate, days: i64) -> Result<Vec<NaiveDate>> {
+    let invalid = || Error::InvalidDateRange { from, days };
+    if !(1..=MAX_RANGE_DAYS).contains(&days) {
+        return Err(invalid());
+    }
+    let to = from
+        .checked_add_signed(Duration::days(days - 1))
+        .ok_or_else(invalid)?;
+    Ok(date_range(from, to))
+}
 
 /// Returns a list of dates starting from the current date and ending 7 days later.
-pub fn get_all_dates() -> Vec<String> {
-    let current_date = FixedOffset::west(7 * 3600).from_utc_datetime(&Utc::now().naive_utc());
-    dates_from_date(current_date)
+pub fn get_all_dates() -> Vec<NaiveDate> {
+    dates_from_date(today())
 }
 
 /// Returns a list of dates starting from a specific date and ending 7 days later.
-fn dates_from_date(current_date: DateTime<FixedOffset>) -> Vec<String> {
+pub fn dates_from_date(current_date: NaiveDate) -> Vec<NaiveDate> {
     (0..7)
         .map(|num| current_date + Duration::days(num))
-        .map(|date| {
-            let year = date.year();
-            let month = date.month();
-            let day = date.day();
-            format!("{}-{:02}-{:02}", year, month, day)
-        })
         .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDate;
+    use chrono::TimeZone;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.ymd(year, month, day).and_hms(hour, minute, 0)
+    }
 
     #[test]
     fn test_dates() {
-        let current_date = FixedOffset::west(7 * 3600)
-            .from_utc_datetime(&NaiveDate::from_ymd(2020, 8, 20).and_hms(10, 0, 0));
         assert_eq!(
-            dates_from_date(current_date),
+            dates_from_date(NaiveDate::from_ymd(2020, 8, 20)),
             vec![
-                "2020-08-20",
-                "2020-08-21",
-                "2020-08-22",
-                "2020-08-23",
-                "2020-08-24",
-                "2020-08-25",
-                "2020-08-26",
+                NaiveDate::from_ymd(2020, 8, 20),
+                NaiveDate::from_ymd(2020, 8, 21),
+                NaiveDate::from_ymd(2020, 8, 22),
+                NaiveDate::from_ymd(2020, 8, 23),
+                NaiveDate::from_ymd(2020, 8, 24),
+                NaiveDate::from_ymd(2020, 8, 25),
+                NaiveDate::from_ymd(2020, 8, 26),
             ]
         );
     }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2021-10-02").unwrap(),
+            NaiveDate::from_ymd(2021, 10, 2)
+        );
+        for date in [
+            "2021-13-45",
+            "2021-02-29",
+            "10/02/2021",
+            "2021-1-5",
+            "2021-10-02x",
+        ] {
+            assert!(matches!(parse_date(date), Err(Error::InvalidDate { date: d }) if d == date));
+        }
+    }
+
+    #[test]
+    fn test_parse_date_range() {
+        // A Wednesday
+        let today = NaiveDate::from_ymd(2021, 10, 6);
+        let single = |date| Some((date, date));
+        let parse = |expr| parse_date_range(expr, today).ok();
+
+        assert_eq!(parse("today"), single(today));
+        assert_eq!(parse("Tomorrow"), single(NaiveDate::from_ymd(2021, 10, 7)));
+        assert_eq!(
+            parse("next-monday"),
+            single(NaiveDate::from_ymd(2021, 10, 11))
+        );
+        assert_eq!(
+            parse("next-wednesday"),
+            single(NaiveDate::from_ymd(2021, 10, 13))
+        );
+        assert_eq!(
+            parse("this-week"),
+            Some((
+                NaiveDate::from_ymd(2021, 10, 4),
+                NaiveDate::from_ymd(2021, 10, 10)
+            ))
+        );
+        assert_eq!(
+            parse("2021-12-01"),
+            single(NaiveDate::from_ymd(2021, 12, 1))
+        );
+        assert!(matches!(
+            parse_date_range("next-someday", today),
+            Err(Error::InvalidDate { date }) if date == "next-someday"
+        ));
+    }
+
+    #[test]
+    fn test_date_range() {
+        let range = date_range(
+            NaiveDate::from_ymd(2021, 12, 30),
+            NaiveDate::from_ymd(2022, 1, 2),
+        );
+        assert_eq!(range.len(), 4);
+        assert_eq!(range[3], NaiveDate::from_ymd(2022, 1, 2));
+        assert!(date_range(range[3], range[0]).is_empty());
+    }
+
+    #[test]
+    fn test_days_from() {
+        let from = NaiveDate::from_ymd(2021, 12, 30);
+        assert_eq!(
+            days_from(from, 4).unwrap(),
+            date_range(from, NaiveDate::from_ymd(2022, 1, 2))
+        );
+        assert_eq!(days_from(from, MAX_RANGE_DAYS).unwrap().len(), 366);
+
+        for days in [0, -1, MAX_RANGE_DAYS + 1, i64::MAX] {
+            assert!(matches!(
+                days_from(from, days),
+                Err(Error::InvalidDateRange { days: d, .. }) if d == days
+            ));
+        }
+        // Ranges running past the last date chrono can represent are rejected, not a panic
+        assert!(days_from(NaiveDate::from_ymd(262143, 12, 31), 2).is_err());
+    }
+
+    #[test]
+    fn test_date_in_standard_time() {
+        // 11:30pm PST is already the next day in UTC-7
+        assert_eq!(
+            date_at(utc(2021, 12, 1, 7, 30)),
+            NaiveDate::from_ymd(2021, 11, 30)
+        );
+        assert_eq!(
+            date_at(utc(2021, 12, 1, 8, 0)),
+            NaiveDate::from_ymd(2021, 12, 1)
+        );
+    }
+
+    #[test]
+    fn test_date_around_spring_forward() {
+        // Clocks go from 2am PST to 3am PDT on 2021-03-14
+        assert_eq!(
+            date_at(utc(2021, 3, 14, 7, 59)),
+            NaiveDate::from_ymd(2021, 3, 13)
+        );
+        assert_eq!(
+            date_at(utc(2021, 3, 14, 8, 0)),
+            NaiveDate::from_ymd(2021, 3, 14)
+        );
+        assert_eq!(
+            date_at(utc(2021, 3, 15, 6, 59)),
+            NaiveDate::from_ymd(2021, 3, 14)
+        );
+        assert_eq!(
+            date_at(utc(2021, 3, 15, 7, 0)),
+            NaiveDate::from_ymd(2021, 3, 15)
+        );
+    }
+
+    #[test]
+    fn test_date_around_fall_back() {
+        // Clocks go from 2am PDT back to 1am PST on 2021-11-07
+        assert_eq!(
+            date_at(utc(2021, 11, 7, 6, 59)),
+            NaiveDate::from_ymd(2021, 11, 6)
+        );
+        assert_eq!(
+            date_at(utc(2021, 11, 7, 7, 0)),
+            NaiveDate::from_ymd(2021, 11, 7)
+        );
+        assert_eq!(
+            date_at(utc(2021, 11, 8, 7, 59)),
+            NaiveDate::from_ymd(2021, 11, 7)
+        );
+        assert_eq!(
+            date_at(utc(2021, 11, 8, 8, 0)),
+            NaiveDate::from_ymd(2021, 11, 8)
+        );
+    }
 }
@@ -0,0 +1,249 @@
+use crate::date::MAX_RANGE_DAYS;
+use crate::parse::ParseError;
+use crate::request::retry::is_retryable_status;
+use chrono::NaiveDate;
+use std::path::{Path, PathBuf};
+
+/// Everything that can go wrong while scraping, along with the page, date or file concerned
+#[derive(Debug)]
+pub enum Error {
+    /// The page could not be fetched, e.g. the connection failed or timed out
+    Network {
+        url: String,
+        attempts: u32,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The server answered with a non-success HTTP status
+    Status {
+        url: String,
+        attempts: u32,
+        status: u16,
+    },
+    /// robots.txt does not allow fetching the page
+    Disallowed { url: String },
+    /// Part of the page could not be parsed
+    Parse { url: String, error: ParseError },
+    /// A url, such as one built from a page or the base url, could not be parsed
+    InvalidUrl {
+        url: String,
+        source: url::ParseError,
+    },
+    /// A date is neither an existing YYYY-MM-DD date nor a relative date expression
+    InvalidDate { date: String },
+    /// A restaurant or meal asked for by name is none of those on the site
+    UnmatchedFilter {
+        kind: String,
+        value: String,
+        options: Vec<String>,
+    },
+    /// A range of dates is empty, too long or runs past the last supported date
+    InvalidDateRange { from: NaiveDate, days: i64 },
+    /// Reading or writing a file failed
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A JSON file could not be read into, or written from, the data it holds
+    Serialization {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn network(url: &str, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Error::Network {
+            url: url.into(),
+            attempts: 1,
+            source: source.into(),
+        }
+    }
+
+    pub fn status(url: &str, status: u16) -> Self {
+        Error::Status {
+            url: url.into(),
+            attempts: 1,
+            status,
+        }
+    }
+
+    pub fn invalid_url(url: &str, source: url::ParseError) -> Self {
+        Error::InvalidUrl {
+            url: url.into(),
+            source,
+        }
+    }
+
+    pub fn io(path: &Path, source: impl Into<std::io::Error>) -> Self {
+        Error::Io {
+            path: path.into(),
+            source: source.into(),
+        }
+    }
+
+    pub fn serialization(path: &Path, source: serde_json::Error) -> Self {
+        Error::Serialization {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Url of the page the error is about, if any
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Error::Network { url, .. }
+            | Error::Status { url, .. }
+            | Error::Disallowed { url }
+            | Error::Parse { url, .. }
+            | Error::InvalidUrl { url, .. } => Some(url),
+            Error::InvalidDate { .. }
+            | Error::UnmatchedFilter { .. }
+            | Error::InvalidDateRange { .. }
+            | Error::Io { .. }
+            | Error::Serialization { .. } => None,
+        }
+    }
+
+    /// Whether the error is transient, i.e. a connection problem, timeout or retryable status
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Status { status, .. } => is_retryable_status(*status),
+            Error::Network { source, .. } => match source.downcast_ref::<reqwest::Error>() {
+                Some(error) => match error.status() {
+                    Some(status) => is_retryable_status(status.as_u16()),
+                    None => error.is_connect() || error.is_timeout(),
+                },
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Record how many attempts were made before giving up on the page
+    pub(crate) fn after_attempts(mut self, count: u32) -> Self {
+        if let Error::Network { attempts, .. } | Error::Status { attempts, .. } = &mut self {
+            *attempts = count;
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Network {
+                url,
+                attempts,
+                source,
+            } => write!(
+                f,
+                "failed to download {} after {} attempt(s): {}",
+                url, attempts, source
+            ),
+            Error::Status {
+                url,
+                attempts,
+                status,
+            } => write!(
+                f,
+                "failed to download {} after {} attempt(s): HTTP status {}",
+                url, attempts, status
+            ),
+            Error::Disallowed { url } => write!(f, "{} is disallowed by robots.txt", url),
+            Error::Parse { url, error } => write!(f, "failed to parse {}: {}", url, error),
+            Error::InvalidUrl { url, source } => write!(f, "invalid url {}: {}", url, source),
+            Error::InvalidDate { date } => {
+                write!(f, "{} is not a valid YYYY-MM-DD or relative date", date)
+            }
+            Error::UnmatchedFilter {
+                kind,
+                value,
+                options,
+            } => write!(
+                f,
+                "no {} matches \"{}\", expected one of: {}",
+                kind,
+                value,
+                options.join(", ")
+            ),
+            Error::InvalidDateRange { from, days } if *days < 1 => write!(
+                f,
+                "dates starting {} must span at least 1 day, not {}",
+                from, days
+            ),
+            Error::InvalidDateRange { from, days } if *days > MAX_RANGE_DAYS => write!(
+                f,
+                "{} days starting {} are more than the {} days allowed",
+                days, from, MAX_RANGE_DAYS
+            ),
+            Error::InvalidDateRange { from, days } => write!(
+                f,
+                "{} days starting {} run past the last supported date",
+                days, from
+            ),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Serialization { path, source } => {
+                write!(f, "{}: invalid JSON: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network { source, .. } => Some(source.as_ref()),
+            Error::Parse { error, .. } => Some(error),
+            Error::InvalidUrl { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            Error::Serialization { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_errors() {
+        let url = "http://menu.dining.ucla.edu/Menus";
+        assert!(Error::status(url, 503).is_retryable());
+        assert!(Error::status(url, 429).is_retryable());
+        assert!(!Error::status(url, 404).is_retryable());
+        assert!(!Error::network(url, "invalid page").is_retryable());
+        let builder = reqwest::Client::new()
+            .get("http://[::1")
+            .build()
+            .unwrap_err();
+        assert!(!Error::network(url, builder).is_retryable());
+        assert!(!Error::Disallowed { url: url.into() }.is_retryable());
+    }
+
+    #[test]
+    fn test_error_context() {
+        let error = Error::status("http://menu.dining.ucla.edu/Menus", 503).after_attempts(3);
+        assert_eq!(error.url(), Some("http://menu.dining.ucla.edu/Menus"));
+        assert_eq!(
+            error.to_string(),
+            "failed to download http://menu.dining.ucla.edu/Menus after 3 attempt(s): HTTP status 503"
+        );
+        assert_eq!(
+            Error::InvalidDate {
+                date: "10/02/2021".into()
+            }
+            .url(),
+            None
+        );
+
+        let error = Error::invalid_url("menus", url::ParseError::RelativeUrlWithoutBase);
+        assert_eq!(error.url(), Some("menus"));
+        assert_eq!(
+            error.to_string(),
+            "invalid url menus: relative URL without a base"
+        );
+    }
+}
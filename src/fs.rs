@@ -0,0 +1,40 @@
+use crate::{Error, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Temporary file next to `path` to write its new contents to, unique within the process so that
+/// concurrent writes of the same file don't share one
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    PathBuf::from(temp)
+}
+
+/// Write a file through a temporary file next to it, which is then renamed over the original, so
+/// an interrupted write never leaves a truncated file behind
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let temp = temp_path(path);
+    std::fs::write(&temp, contents).map_err(|e| Error::io(&temp, e))?;
+    std::fs::rename(&temp, path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        Error::io(path, e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_paths_are_unique() {
+        let path = Path::new("cache/page.html");
+        let (first, second) = (temp_path(path), temp_path(path));
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), path.parent());
+    }
+}
@@ -1,5 +1,9 @@
+pub mod calendar;
+pub mod date;
+pub mod error;
+mod fs;
 pub mod model;
 pub mod parse;
 pub mod request;
 
-mod date;
+pub use error::{Error, Result};
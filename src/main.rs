@@ -1,14 +1,23 @@
-mod date;
-mod model;
-mod parse;
-mod request;
-
+use chrono::NaiveDate;
 use clap::{App, Arg, ArgMatches};
-use model::storage::Storage;
-use model::{DateMenu, RestaurantMenu};
-use parse::parse_item;
-use request::Downloadable;
 use std::fs::OpenOptions;
+use std::path::Path;
+use ucla_dining_scraper::calendar::{AcademicCalendar, CalendarDay, Service};
+use ucla_dining_scraper::date;
+use ucla_dining_scraper::model::catalog::ItemCatalog;
+use ucla_dining_scraper::model::storage::Storage;
+use ucla_dining_scraper::model::{DateMenu, RestaurantEnum};
+use ucla_dining_scraper::request::cache::CachingTransport;
+use ucla_dining_scraper::request::cassette::{RecordingTransport, ReplayTransport};
+use ucla_dining_scraper::request::politeness::{
+    PoliteTransport, DEFAULT_USER_AGENT, MAX_RATE, MIN_RATE,
+};
+use ucla_dining_scraper::request::transport::{ReqwestTransport, Transport};
+use ucla_dining_scraper::request::{self, DownloadConfig};
+use ucla_dining_scraper::Error;
+
+/// Upper bound on attempts per page, as exponential backoff makes more of them pointless
+const MAX_ATTEMPTS: u32 = 11;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -21,7 +30,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .short("a")
                 .long("all")
                 .help("Download all menus starting from current date")
-                .conflicts_with("date"),
+                .conflicts_with_all(&["date", "from", "to", "days"]),
         )
         .arg(
             Arg::with_name("with-details")
@@ -29,12 +38,135 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .long("with-details")
                 .help("Download menus along all item details"),
         )
+        .arg(
+            Arg::with_name("catalog")
+                .long("catalog")
+                .takes_value(true)
+                .requires("with-details")
+                .help(
+                    "Reuse item details stored in this file and save newly downloaded ones to it",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-in-flight")
+                .long("max-in-flight")
+                .takes_value(true)
+                .help("Maximum number of pages to download at the same time (default 8)"),
+        )
+        .arg(
+            Arg::with_name("retries")
+                .long("retries")
+                .takes_value(true)
+                .help("Number of times a failed download is retried (default 2, at most 10)"),
+        )
+        .arg(
+            Arg::with_name("base-url")
+                .long("base-url")
+                .takes_value(true)
+                .help("Scrape a mirror of the dining site at this url instead"),
+        )
+        .arg(
+            Arg::with_name("restaurant")
+                .long("restaurant")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only download menus of this restaurant, by name or url slug (repeatable)"),
+        )
+        .arg(
+            Arg::with_name("meal")
+                .long("meal")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only download menus of this meal, such as Dinner (repeatable)"),
+        )
+        .arg(
+            Arg::with_name("cache")
+                .long("cache")
+                .takes_value(true)
+                .conflicts_with("replay")
+                .help("Cache downloaded pages in this directory and revalidate them on later runs"),
+        )
+        .arg(
+            Arg::with_name("refresh")
+                .long("refresh")
+                .requires("cache")
+                .help("Download all pages again instead of using the cache"),
+        )
+        .arg(
+            Arg::with_name("user-agent")
+                .long("user-agent")
+                .takes_value(true)
+                .help("User-Agent header sent with every request"),
+        )
+        .arg(
+            Arg::with_name("rate").long("rate").takes_value(true).help(
+                "Maximum number of requests per second to each host (default 4, 0.01 to 1000)",
+            ),
+        )
+        .arg(
+            Arg::with_name("respect-robots")
+                .long("respect-robots")
+                .help("Skip pages disallowed by the site's robots.txt"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .takes_value(true)
+                .conflicts_with("replay")
+                .help("Store a copy of every downloaded page in this directory"),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .takes_value(true)
+                .help("Serve pages from a directory written by --record instead of the network"),
+        )
         .arg(
             Arg::with_name("date")
                 .long("date")
-                .required_unless("all")
+                .required_unless_one(&["all", "from", "to", "days"])
+                .conflicts_with("from")
+                .takes_value(true)
+                .help(
+                    "Specify the date (YYYY-MM-DD, today, tomorrow, next-monday, ..., this-week) \
+                     for which menu to download",
+                ),
+        )
+        .arg(
+            Arg::with_name("from")
+                .long("from")
                 .takes_value(true)
-                .help("Specify the date (YYYY-MM-DD) for which menu to download"),
+                .help("Download menus starting from this date (default today)"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .conflicts_with("days")
+                .help("Download menus up to and including this date"),
+        )
+        .arg(
+            Arg::with_name("days")
+                .long("days")
+                .takes_value(true)
+                .help("Number of days of menus to download, at most 366"),
+        )
+        .arg(
+            Arg::with_name("calendar")
+                .long("calendar")
+                .takes_value(true)
+                .help(
+                    "Annotate menus with the quarter and week from this academic calendar \
+                     (such as data/academic_calendar.json)",
+                ),
+        )
+        .arg(
+            Arg::with_name("skip-closed")
+                .long("skip-closed")
+                .requires("calendar")
+                .help("Skip dates on which the academic calendar says the dining halls are closed"),
         )
         .arg(
             Arg::with_name("save")
@@ -56,24 +188,121 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 async fn run(app: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
-    let dates = get_dates(app);
+    let calendar = match app.value_of("calendar") {
+        Some(path) => Some(AcademicCalendar::load(Path::new(path))?),
+        None => None,
+    };
+    let mut dates = get_dates(app)?;
+    if let (Some(calendar), true) = (&calendar, app.is_present("skip-closed")) {
+        dates = calendar.service_dates(dates);
+    }
+    let config = get_download_config(app)?;
+    let transport = get_transport(app)?;
+    let transport = transport.as_ref();
+    let restaurants = get_restaurants(transport, &config).await;
+    config.filter.check_restaurants(&restaurants)?;
+    let mut catalog = load_catalog(app)?;
+    let mut served = Vec::new();
     for date in dates {
-        print!("Fetching menus for {} ... \t", date);
-        if let Ok(menu) = request::download_menus(date).await {
+        let day = calendar.as_ref().map(|calendar| calendar.day(date));
+        match &day {
+            // Flag days the halls don't run their regular schedule
+            Some(CalendarDay {
+                service,
+                note: Some(note),
+                ..
+            }) if *service != Service::Regular => print!(
+                "Fetching menus for {} ({}: {}) ... \t",
+                date,
+                service.name(),
+                note
+            ),
+            _ => print!("Fetching menus for {} ... \t", date),
+        }
+        let (mut menu, meals, errors) =
+            request::download_menus(date, &restaurants, transport, &config).await;
+        menu.calendar = day;
+        for meal in meals {
+            if !served.contains(&meal) {
+                served.push(meal);
+            }
+        }
+        if errors.is_empty() {
             println!("[done]");
-            if let Ok(()) = save(app, &menu) {
-                println!("[done]");
-            } else {
+        } else {
+            println!("[{} FAILED]", errors.len());
+            for error in errors {
+                eprintln!("  {}", error);
+            }
+        }
+        if app.is_present("with-details") {
+            inflate_item_details(&mut menu, &mut catalog, transport, &config).await;
+        }
+        match save(app, &menu) {
+            Ok(()) => println!("[done]"),
+            Err(error) => {
                 println!("[FAILED]");
+                eprintln!("  {}", error);
             }
-        } else {
-            println!("[FAILED]");
         }
     }
+    save_catalog(app, &catalog)?;
+    // Meals vary from day to day, so a meal asked for is only an error if no day served it
+    if !served.is_empty() {
+        config.filter.check_meals(&served)?;
+    }
+    Ok(())
+}
+
+async fn inflate_item_details(
+    menu: &mut DateMenu,
+    catalog: &mut ItemCatalog,
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) {
+    print!("Fetching item details for {} ... \t", menu.date);
+    let errors = request::inflate_item_details(menu, catalog, transport, config).await;
+    if errors.is_empty() {
+        println!("[done]");
+    } else {
+        println!("[{} FAILED]", errors.len());
+        for error in errors {
+            eprintln!("  {}", error);
+        }
+    }
+}
+
+fn load_catalog(app: &ArgMatches) -> ucla_dining_scraper::Result<ItemCatalog> {
+    match app.value_of("catalog") {
+        // A missing catalog file is expected on the first run
+        Some(path) if Path::new(path).exists() => {
+            print!("Loading item catalog from {} ... \t", path);
+            // Carrying on with an empty catalog would overwrite the existing one when saving it
+            match ItemCatalog::load(Path::new(path)) {
+                Ok(catalog) => {
+                    println!("[done]");
+                    Ok(catalog)
+                }
+                Err(error) => {
+                    println!("[FAILED]");
+                    Err(error)
+                }
+            }
+        }
+        _ => Ok(ItemCatalog::new()),
+    }
+}
+
+fn save_catalog(app: &ArgMatches, catalog: &ItemCatalog) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = app.value_of("catalog") {
+        print!("Storing item catalog to {} ... \t", path);
+        catalog.save(Path::new(path))?;
+        println!("[done]");
+    }
     Ok(())
 }
 
-fn save(app: &ArgMatches, menu: &DateMenu) -> Result<(), Box<dyn std::error::Error>> {
+fn save(app: &ArgMatches, menu: &DateMenu) -> ucla_dining_scraper::Result<()> {
     if app.is_present("save") || app.is_present("save-pretty") {
         // Get directory for which to save downloaded data
         let dir = {
@@ -85,48 +314,147 @@ fn save(app: &ArgMatches, menu: &DateMenu) -> Result<(), Box<dyn std::error::Err
         };
         print!("Storing menus for {} on disk to {} ... \t", menu.date, dir);
 
-        save_json(&menu, dir, app.is_present("save-pretty"))?;
+        save_json(menu, dir, app.is_present("save-pretty"))?;
     }
 
     Ok(())
 }
 
-fn save_json(menu: &DateMenu, dir: &str, pretty: bool) -> Result<(), Box<dyn std::error::Error>> {
+fn save_json(menu: &DateMenu, dir: &str, pretty: bool) -> ucla_dining_scraper::Result<()> {
     let suffix = if pretty { "-pretty" } else { "" };
-    let filename = format!("{}{}", menu.date, suffix);
+    let path = Path::new(dir).join(format!("{}{}", menu.date, suffix));
     let file = OpenOptions::new()
         .create(true)
         .truncate(true)
         .write(true)
-        .open(format!("{}/{}", dir, filename))?;
+        .open(&path)
+        .map_err(|e| Error::io(&path, e))?;
 
     if pretty {
-        serde_json::to_writer_pretty(file, &menu.to_json())?;
+        serde_json::to_writer_pretty(file, &menu.to_json())
     } else {
-        serde_json::to_writer(file, &menu.to_json_min())?;
+        serde_json::to_writer(file, &menu.to_json_min())
+    }
+    .map_err(|e| Error::serialization(&path, e))
+}
+
+fn get_download_config(app: &ArgMatches) -> Result<DownloadConfig, Box<dyn std::error::Error>> {
+    let mut config = DownloadConfig::default();
+    if let Some(max_in_flight) = app.value_of("max-in-flight") {
+        config.max_in_flight = match max_in_flight.parse() {
+            Ok(max_in_flight) if max_in_flight > 0 => max_in_flight,
+            _ => {
+                let message = format!(
+                    "--max-in-flight must be a positive number, not {}",
+                    max_in_flight
+                );
+                return Err(message.into());
+            }
+        };
+    }
+    if let Some(base_url) = app.value_of("base-url") {
+        config.base_url = request::parse_base_url(base_url)?;
+    }
+    if let Some(retries) = app.value_of("retries") {
+        config.retry.max_attempts = match retries.parse::<u32>()?.checked_add(1) {
+            Some(attempts) if attempts <= MAX_ATTEMPTS => attempts,
+            _ => return Err(format!("--retries must be at most {}", MAX_ATTEMPTS - 1).into()),
+        };
     }
+    if let Some(restaurants) = app.values_of("restaurant") {
+        config.filter.restaurants = restaurants.map(String::from).collect();
+    }
+    if let Some(meals) = app.values_of("meal") {
+        config.filter.meals = meals.map(String::from).collect();
+    }
+    Ok(config)
+}
 
-    Ok(())
+fn get_transport(app: &ArgMatches) -> Result<Box<dyn Transport>, Box<dyn std::error::Error>> {
+    if let Some(dir) = app.value_of("replay") {
+        return Ok(Box::new(ReplayTransport::new(dir.into())));
+    }
+
+    let user_agent = app.value_of("user-agent").unwrap_or(DEFAULT_USER_AGENT);
+    let client = reqwest::Client::builder().user_agent(user_agent).build()?;
+
+    let rate = match app.value_of("rate") {
+        Some(rate) => rate.parse::<f64>()?,
+        None => 4.0,
+    };
+    if !(MIN_RATE..=MAX_RATE).contains(&rate) {
+        return Err(format!("--rate must be between {} and {}", MIN_RATE, MAX_RATE).into());
+    }
+    // Allow a burst of about a second worth of requests
+    let transport = PoliteTransport::new(
+        ReqwestTransport::with_client(client),
+        user_agent.into(),
+        rate,
+        rate.ceil() as u32,
+    )
+    .respect_robots_txt(app.is_present("respect-robots"));
+
+    // Pages served from the cache don't touch the network, so they aren't throttled
+    let transport: Box<dyn Transport> = match app.value_of("cache") {
+        Some(dir) => Box::new(
+            CachingTransport::new(transport, dir.into()).force_refresh(app.is_present("refresh")),
+        ),
+        None => Box::new(transport),
+    };
+
+    Ok(match app.value_of("record") {
+        Some(dir) => Box::new(RecordingTransport::new(transport, dir.into())),
+        None => Box::new(transport),
+    })
 }
 
-async fn inflate_item_details(menu: &mut RestaurantMenu) -> Result<(), Box<dyn std::error::Error>> {
-    // Download all item details and inflate placeholders in Menu object
-    for section in &mut menu.sections {
-        for item in &mut section.items {
-            item.set_details(parse_item::parse(
-                item.details_request().download().await?.as_str(),
-            ))
+async fn get_restaurants(
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) -> Vec<RestaurantEnum> {
+    print!("Discovering restaurants ... \t");
+    let (restaurants, errors) = match request::discover_restaurants(transport, config).await {
+        Ok(discovered) => discovered,
+        Err(error) => (Vec::new(), vec![error]),
+    };
+    if errors.is_empty() {
+        println!("[done]");
+    } else {
+        println!("[{} FAILED]", errors.len());
+        for error in errors {
+            eprintln!("  {}", error);
         }
     }
-    Ok(())
+    if restaurants.is_empty() {
+        // Fall back to the restaurants we already know about
+        RestaurantEnum::well_known()
+    } else {
+        restaurants
+    }
 }
 
-fn get_dates(app: &ArgMatches) -> Vec<String> {
-    // Get all menu requests starting from today until a week later
+fn get_dates(app: &ArgMatches) -> Result<Vec<NaiveDate>, Box<dyn std::error::Error>> {
+    // Relative dates are resolved against today in Los Angeles
+    let today = date::today();
     if app.is_present("all") {
-        return date::get_all_dates();
-    } else {
-        let date = app.value_of("date").unwrap();
-        return vec![date.to_string()];
+        return Ok(date::dates_from_date(today));
     }
+
+    let (from, last) = match app.value_of("from").or_else(|| app.value_of("date")) {
+        Some(expr) => date::parse_date_range(expr, today)?,
+        None => (today, today),
+    };
+    let days = if let Some(days) = app.value_of("days") {
+        days.parse::<i64>()?
+    } else {
+        let to = match app.value_of("to") {
+            Some(to) => date::parse_date_range(to, today)?.1,
+            None => last,
+        };
+        if to < from {
+            return Err(format!("--to date {} is before {}", to, from).into());
+        }
+        (to - from).num_days() + 1
+    };
+    Ok(date::days_from(from, days)?)
 }
@@ -0,0 +1,91 @@
+use crate::fs::write_atomic;
+use crate::model::ItemDetails;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+/// Item details keyed by recipe id, so each recipe page only has to be downloaded once
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct ItemCatalog {
+    details: BTreeMap<String, ItemDetails>,
+}
+
+impl ItemCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a catalog previously written with `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|e| Error::io(path, e))?;
+        serde_json::from_reader(file).map_err(|e| Error::serialization(path, e))
+    }
+
+    /// Store the catalog, replacing the file atomically so an interrupted run keeps the old one
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec(self).map_err(|e| Error::serialization(path, e))?;
+        write_atomic(path, &json)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemDetails> {
+        self.details.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.details.contains_key(id)
+    }
+
+    pub fn insert(&mut self, id: String, details: ItemDetails) {
+        self.details.insert(id, details);
+    }
+
+    pub fn len(&self) -> usize {
+        self.details.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.details.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_save_load() {
+        let mut catalog = ItemCatalog::new();
+        catalog.insert(
+            "977026".into(),
+            ItemDetails {
+                description: Some("Italian Minestrone Soup".into()),
+                ..Default::default()
+            },
+        );
+        assert!(catalog.contains("977026"));
+        assert!(!catalog.contains("977085"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item-catalog.json");
+        catalog.save(&path).unwrap();
+        let loaded = ItemCatalog::load(&path).unwrap();
+
+        assert_eq!(loaded, catalog);
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[test]
+    fn test_catalog_load_malformed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("item-catalog.json");
+        std::fs::write(&path, "{\"details\": [").unwrap();
+        let loaded = ItemCatalog::load(&path);
+
+        assert!(matches!(loaded, Err(Error::Serialization { .. })));
+    }
+}
@@ -1,4 +1,8 @@
-use crate::model::{Item, ItemDetails, RestaurantMenu, Section};
+use crate::model::{
+    Availability, CarbonFootprint, Item, ItemDetails, Nutrient, NutritionFacts, RestaurantMenu,
+    Section,
+};
+use itertools::Itertools;
 use std::fmt;
 
 impl fmt::Display for RestaurantMenu {
@@ -11,6 +15,9 @@ impl fmt::Display for RestaurantMenu {
             self.restaurant.name()
         )?;
         writeln!(f, "---------------------------------")?;
+        if self.availability != Availability::Open {
+            writeln!(f, "{}", self.availability.name())?;
+        }
 
         for section in &self.sections {
             writeln!(f, "{}", section)?;
@@ -32,8 +39,21 @@ impl fmt::Display for Section {
 impl fmt::Display for Item {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "  ID: {}", self.id)?;
-        writeln!(f, "  Name: {}", self.name)?;
+        writeln!(f, "  Name: {}", self.full_name())?;
         writeln!(f, "  Recipe Link: {}", self.recipe_link)?;
+        if !self.web_codes.is_empty() {
+            writeln!(
+                f,
+                "  Web Codes: {}",
+                self.web_codes.iter().map(|c| c.code()).join(", ")
+            )?;
+        }
+        if let Some(summary) = &self.summary {
+            writeln!(f, "  Summary: {}", summary)?;
+        }
+        if self.carbon_footprint != CarbonFootprint::Unknown {
+            writeln!(f, "  Carbon Footprint: {}", self.carbon_footprint.name())?;
+        }
         writeln!(
             f,
             "  {}",
@@ -67,6 +87,44 @@ impl fmt::Display for ItemDetails {
             self.allergens
                 .as_ref()
                 .unwrap_or(&"no allergens".to_string())
+        )?;
+        write!(
+            f,
+            "  Nutrition: {}",
+            match &self.nutrition {
+                Some(nutrition) => nutrition.to_string(),
+                None => "no nutrition facts".to_string(),
+            }
         )
     }
 }
+
+impl fmt::Display for NutritionFacts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} per {}",
+            self.calories
+                .map(|c| format!("{} calories", c))
+                .unwrap_or_else(|| "unknown calories".to_string()),
+            self.serving_size.as_deref().unwrap_or("serving")
+        )?;
+        let nutrients = [
+            ("fat", &self.total_fat),
+            ("carbs", &self.total_carbohydrate),
+            ("protein", &self.protein),
+        ];
+        for (name, nutrient) in nutrients.iter() {
+            if let Some(nutrient) = nutrient {
+                write!(f, ", {} {}", nutrient, name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Nutrient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.amount, self.unit)
+    }
+}
@@ -1,42 +1,59 @@
+pub mod catalog;
 pub mod display;
 pub mod storage;
 
+use crate::calendar::CalendarDay;
 use crate::request::item::ItemRequest;
+use chrono::{NaiveDate, NaiveTime};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use strum_macros::EnumIter;
+use url::Url;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct DateMenu {
-    pub date: String,
+    pub date: NaiveDate,
     pub restaurants: Vec<Menu>,
+    /// Where the date falls in the academic calendar, when one was consulted
+    #[serde(default)]
+    pub calendar: Option<CalendarDay>,
 }
 
 impl DateMenu {
+    /// Add a meal to the menu, including closed and failed ones so consumers can tell them apart
+    /// from meals that were never requested
     pub fn add_restaurant(&mut self, rm: RestaurantMenu) {
-        if rm.sections.is_empty() {
-            return;
-        }
-
-        if let Some(pos) = self
+        let pos = self
             .restaurants
             .iter()
-            .position(|r| r.name == rm.restaurant)
-        {
-            let restaurant = &mut self.restaurants[pos];
-            restaurant.meals.push(MenuMeal {
-                name: rm.meal,
-                sections: rm.sections,
-            });
+            .position(|r| r.name == rm.restaurant);
+        let meal = MenuMeal {
+            name: rm.meal,
+            availability: rm.availability,
+            hours: None,
+            sections: rm.sections,
+        };
+        if let Some(pos) = pos {
+            self.restaurants[pos].meals.push(meal);
         } else {
-            let mut restaurant = Menu {
+            self.restaurants.push(Menu {
                 name: rm.restaurant,
-                meals: Vec::new(),
-            };
-            restaurant.meals.push(MenuMeal {
-                name: rm.meal,
-                sections: rm.sections,
+                meals: vec![meal],
             });
-            self.restaurants.push(restaurant);
+        }
+    }
+
+    /// Attach opening hours to the matching meals of the menu
+    pub fn set_hours(&mut self, hours: &[MealHours]) {
+        for meal_hours in hours {
+            let meal = self
+                .restaurants
+                .iter_mut()
+                .filter(|r| r.name == meal_hours.restaurant)
+                .flat_map(|r| r.meals.iter_mut())
+                .find(|m| m.name == meal_hours.meal);
+            if let Some(meal) = meal {
+                meal.hours = Some(meal_hours.hours);
+            }
         }
     }
 }
@@ -47,39 +64,197 @@ pub struct Menu {
     pub meals: Vec<MenuMeal>,
 }
 
+impl Menu {
+    /// The meal being served at the given time, if any
+    pub fn open_at(&self, time: NaiveTime) -> Option<&MenuMeal> {
+        self.meals
+            .iter()
+            .find(|m| matches!(m.hours, Some(hours) if hours.contains(time)))
+    }
+
+    pub fn carbon_footprint(&self) -> CarbonFootprintSummary {
+        let mut summary = CarbonFootprintSummary::default();
+        for meal in &self.meals {
+            summary.merge(&meal.carbon_footprint());
+        }
+        summary
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct MenuMeal {
     pub name: MealEnum,
+    #[serde(default)]
+    pub availability: Availability,
+    #[serde(default)]
+    pub hours: Option<Hours>,
     pub sections: Vec<Section>,
 }
 
+impl MenuMeal {
+    pub fn carbon_footprint(&self) -> CarbonFootprintSummary {
+        let mut summary = CarbonFootprintSummary::default();
+        for item in self.sections.iter().flat_map(|s| &s.items) {
+            summary.add(item.carbon_footprint);
+            for accompaniment in &item.accompaniments {
+                summary.add(accompaniment.carbon_footprint);
+            }
+        }
+        summary
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct RestaurantMenu {
-    pub date: String,
+    pub date: NaiveDate,
     pub restaurant: RestaurantEnum,
     pub meal: MealEnum,
+    #[serde(default)]
+    pub availability: Availability,
     pub sections: Vec<Section>,
 }
 
+impl RestaurantMenu {
+    /// Placeholder for a menu whose page could not be downloaded
+    pub fn failed(date: NaiveDate, restaurant: RestaurantEnum, meal: MealEnum) -> Self {
+        RestaurantMenu {
+            date,
+            restaurant,
+            meal,
+            availability: Availability::FetchFailed,
+            sections: Vec::new(),
+        }
+    }
+}
+
+/// Time span during which a meal is served
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct Hours {
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+}
+
+impl Hours {
+    /// Whether the time falls in the span, which may run past midnight
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.open <= self.close {
+            self.open <= time && time < self.close
+        } else {
+            self.open <= time || time < self.close
+        }
+    }
+}
+
+/// Hours of a meal at a restaurant, as listed on the hours page
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct MealHours {
+    pub restaurant: RestaurantEnum,
+    pub meal: MealEnum,
+    pub hours: Hours,
+}
+
+/// Whether a meal is served, as far as the scraper could tell
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum Availability {
+    /// A menu is posted, listing the sections served
+    Open,
+    /// The hall is closed or has not posted a menu for the meal
+    Closed,
+    /// The menu page could not be downloaded
+    FetchFailed,
+    /// The page lists no sections but doesn't say the hall is closed either, which is also
+    /// assumed for menus stored before availability was recorded
+    #[default]
+    Unknown,
+}
+
+impl Availability {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Open => "Open".into(),
+            Self::Closed => "Closed".into(),
+            Self::FetchFailed => "Fetch Failed".into(),
+            Self::Unknown => "Unknown".into(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Section {
     pub name: String,
     pub items: Vec<Item>,
 }
 
-#[derive(Debug, EnumIter, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum RestaurantEnum {
     BruinPlate,
     DeNeve,
     Epicuria,
+    FeastAtRieber,
+    BruinCafe,
+    Cafe1919,
+    Rendezvous,
+    TheStudy,
+    TheDrey,
+    /// A dining location found on the dining site that has no variant of its own
+    Other {
+        name: String,
+        url_name: String,
+    },
 }
 
 impl RestaurantEnum {
+    /// All restaurants with a variant of their own, used when discovery is not possible
+    pub fn well_known() -> Vec<RestaurantEnum> {
+        vec![
+            Self::BruinPlate,
+            Self::DeNeve,
+            Self::Epicuria,
+            Self::FeastAtRieber,
+            Self::BruinCafe,
+            Self::Cafe1919,
+            Self::Rendezvous,
+            Self::TheStudy,
+            Self::TheDrey,
+        ]
+    }
+
+    /// Maps a URL slug from the dining site to a restaurant, keeping unknown slugs as `Other`
+    pub fn from_url_name(url_name: &str, name: &str) -> Self {
+        Self::well_known()
+            .into_iter()
+            .find(|r| r.url_name() == url_name)
+            .unwrap_or_else(|| Self::Other {
+                name: name.into(),
+                url_name: url_name.into(),
+            })
+    }
+
+    /// Looks up a restaurant by its name or URL slug, ignoring case, keeping unknown ones as `Other`
+    pub fn from_name(name: &str) -> Self {
+        Self::well_known()
+            .into_iter()
+            .find(|r| {
+                r.name().eq_ignore_ascii_case(name) || r.url_name().eq_ignore_ascii_case(name)
+            })
+            .unwrap_or_else(|| Self::Other {
+                name: name.into(),
+                url_name: name.into(),
+            })
+    }
+
     pub fn name(&self) -> String {
         match self {
             Self::BruinPlate => "Bruin Plate".into(),
             Self::DeNeve => "De Neve".into(),
             Self::Epicuria => "Epicuria".into(),
+            Self::FeastAtRieber => "Feast at Rieber".into(),
+            Self::BruinCafe => "Bruin Café".into(),
+            Self::Cafe1919 => "Café 1919".into(),
+            Self::Rendezvous => "Rendezvous".into(),
+            Self::TheStudy => "The Study at Hedrick".into(),
+            Self::TheDrey => "The Drey".into(),
+            Self::Other { name, .. } => name.clone(),
         }
     }
 
@@ -88,58 +263,562 @@ impl RestaurantEnum {
             Self::BruinPlate => "BruinPlate".into(),
             Self::DeNeve => "DeNeve".into(),
             Self::Epicuria => "Epicuria".into(),
+            Self::FeastAtRieber => "FeastAtRieber".into(),
+            Self::BruinCafe => "BruinCafe".into(),
+            Self::Cafe1919 => "Cafe1919".into(),
+            Self::Rendezvous => "Rendezvous".into(),
+            Self::TheStudy => "HedrickStudy".into(),
+            Self::TheDrey => "Drey".into(),
+            Self::Other { url_name, .. } => url_name.clone(),
         }
     }
 }
 
-#[derive(Debug, EnumIter, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum MealEnum {
     Breakfast,
+    Brunch,
     Lunch,
     Dinner,
+    ExtendedDinner,
+    LateNight,
+    /// A meal period found on a menu page that has no variant of its own
+    Other {
+        name: String,
+        url_name: String,
+    },
 }
 
 impl MealEnum {
+    /// All meal periods with a variant of their own
+    pub fn well_known() -> Vec<MealEnum> {
+        vec![
+            Self::Breakfast,
+            Self::Brunch,
+            Self::Lunch,
+            Self::Dinner,
+            Self::ExtendedDinner,
+            Self::LateNight,
+        ]
+    }
+
+    /// Meal periods served on a regular weekday, used when the meal tabs can't be discovered
+    pub fn defaults() -> Vec<MealEnum> {
+        vec![Self::Breakfast, Self::Lunch, Self::Dinner]
+    }
+
+    /// Maps a URL slug from the dining site to a meal, keeping unknown slugs as `Other`
+    pub fn from_url_name(url_name: &str, name: &str) -> Self {
+        Self::well_known()
+            .into_iter()
+            .find(|m| m.url_name() == url_name)
+            .unwrap_or_else(|| Self::Other {
+                name: name.into(),
+                url_name: url_name.into(),
+            })
+    }
+
+    /// Looks up a meal by its name or URL slug, ignoring case, keeping unknown ones as `Other`
+    pub fn from_name(name: &str) -> Self {
+        Self::well_known()
+            .into_iter()
+            .find(|m| {
+                m.name().eq_ignore_ascii_case(name) || m.url_name().eq_ignore_ascii_case(name)
+            })
+            .unwrap_or_else(|| Self::Other {
+                name: name.into(),
+                url_name: name.into(),
+            })
+    }
+
     pub fn name(&self) -> String {
         match self {
             Self::Breakfast => "Breakfast".into(),
+            Self::Brunch => "Brunch".into(),
             Self::Lunch => "Lunch".into(),
             Self::Dinner => "Dinner".into(),
+            Self::ExtendedDinner => "Extended Dinner".into(),
+            Self::LateNight => "Late Night".into(),
+            Self::Other { name, .. } => name.clone(),
         }
     }
 
     pub fn url_name(&self) -> String {
         match self {
             Self::Breakfast => "Breakfast".into(),
+            Self::Brunch => "Brunch".into(),
             Self::Lunch => "Lunch".into(),
             Self::Dinner => "Dinner".into(),
+            Self::ExtendedDinner => "ExtendedDinner".into(),
+            Self::LateNight => "LateNight".into(),
+            Self::Other { url_name, .. } => url_name.clone(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct Item {
     pub id: String,
     pub name: String,
     pub recipe_link: String,
+    #[serde(default)]
+    pub web_codes: Vec<WebCode>,
+    #[serde(default)]
+    pub carbon_footprint: CarbonFootprint,
+    /// Short description from the menu listing tooltip, available without fetching details
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Items listed with a "w/" prefix that are served together with this one
+    #[serde(default)]
+    pub accompaniments: Vec<Item>,
     pub details: Option<ItemDetails>,
 }
 
 impl Item {
-    pub fn details_request(&self) -> ItemRequest {
-        ItemRequest {
-            id: self.id.clone(),
-        }
+    pub fn details_request(&self, base: &Url) -> ItemRequest {
+        ItemRequest::new(base.clone(), self.id.clone())
     }
 
     pub fn set_details(&mut self, details: ItemDetails) {
+        // The recipe page may list codes that the menu listing left out
+        for code in &details.web_codes {
+            if !self.web_codes.contains(code) {
+                self.web_codes.push(code.clone());
+            }
+        }
+        self.carbon_footprint = CarbonFootprint::from_web_codes(&self.web_codes);
         self.details = Some(details);
     }
+
+    /// Name of the whole dish, e.g. "Fusilli Fruiti De Mari with Toasted Herb & Cheese Bread"
+    pub fn full_name(&self) -> String {
+        if self.accompaniments.is_empty() {
+            return self.name.clone();
+        }
+        format!(
+            "{} with {}",
+            self.name,
+            self.accompaniments
+                .iter()
+                .map(|a| a.name.as_str())
+                .join(" and ")
+        )
+    }
+
+    /// Description from the recipe page if downloaded, otherwise the menu tooltip summary
+    pub fn description(&self) -> Option<&str> {
+        self.details
+            .as_ref()
+            .and_then(|d| d.description.as_deref())
+            .or(self.summary.as_deref())
+    }
+
+    pub fn has_web_code(&self, code: &WebCode) -> bool {
+        self.web_codes.contains(code)
+    }
+
+    pub fn is_vegan(&self) -> bool {
+        self.has_web_code(&WebCode::Vegan)
+    }
+
+    pub fn is_vegetarian(&self) -> bool {
+        self.is_vegan() || self.has_web_code(&WebCode::Vegetarian)
+    }
+
+    pub fn allergens(&self) -> Vec<&WebCode> {
+        self.web_codes.iter().filter(|c| c.is_allergen()).collect()
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default, Clone)]
 pub struct ItemDetails {
     pub description: Option<String>,
     pub ingredients: Option<String>,
     pub allergens: Option<String>,
+    #[serde(default)]
+    pub web_codes: Vec<WebCode>,
+    #[serde(default)]
+    pub nutrition: Option<NutritionFacts>,
+}
+
+/// Dietary, allergen and sustainability codes shown as icons next to menu items
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[serde(from = "String", into = "String")]
+pub enum WebCode {
+    Vegan,
+    Vegetarian,
+    Halal,
+    ContainsPeanuts,
+    ContainsTreeNuts,
+    ContainsWheat,
+    ContainsGluten,
+    ContainsSoy,
+    ContainsDairy,
+    ContainsEggs,
+    ContainsShellfish,
+    ContainsFish,
+    ContainsSesame,
+    LowCarbon,
+    HighCarbon,
+    /// A code this crate does not know about yet, kept verbatim
+    Other(String),
+}
+
+impl WebCode {
+    pub fn from_code(code: &str) -> Self {
+        match code.trim().to_uppercase().as_str() {
+            "VG" => Self::Vegan,
+            "V" => Self::Vegetarian,
+            "HAL" => Self::Halal,
+            "APNT" => Self::ContainsPeanuts,
+            "ATNT" => Self::ContainsTreeNuts,
+            "AWHT" => Self::ContainsWheat,
+            "AGTN" => Self::ContainsGluten,
+            "ASOY" => Self::ContainsSoy,
+            "AMLK" => Self::ContainsDairy,
+            "AEGG" => Self::ContainsEggs,
+            "ACSF" => Self::ContainsShellfish,
+            "AFSH" => Self::ContainsFish,
+            "ASES" => Self::ContainsSesame,
+            "LC" => Self::LowCarbon,
+            "HC" => Self::HighCarbon,
+            _ => Self::Other(code.trim().into()),
+        }
+    }
+
+    pub fn code(&self) -> String {
+        match self {
+            Self::Vegan => "VG".into(),
+            Self::Vegetarian => "V".into(),
+            Self::Halal => "HAL".into(),
+            Self::ContainsPeanuts => "APNT".into(),
+            Self::ContainsTreeNuts => "ATNT".into(),
+            Self::ContainsWheat => "AWHT".into(),
+            Self::ContainsGluten => "AGTN".into(),
+            Self::ContainsSoy => "ASOY".into(),
+            Self::ContainsDairy => "AMLK".into(),
+            Self::ContainsEggs => "AEGG".into(),
+            Self::ContainsShellfish => "ACSF".into(),
+            Self::ContainsFish => "AFSH".into(),
+            Self::ContainsSesame => "ASES".into(),
+            Self::LowCarbon => "LC".into(),
+            Self::HighCarbon => "HC".into(),
+            Self::Other(code) => code.clone(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Vegan => "Vegan Menu Option".into(),
+            Self::Vegetarian => "Vegetarian Menu Option".into(),
+            Self::Halal => "Halal Menu Option".into(),
+            Self::ContainsPeanuts => "Contains Peanuts".into(),
+            Self::ContainsTreeNuts => "Contains Tree Nuts".into(),
+            Self::ContainsWheat => "Contains Wheat".into(),
+            Self::ContainsGluten => "Contains Gluten".into(),
+            Self::ContainsSoy => "Contains Soy".into(),
+            Self::ContainsDairy => "Contains Dairy".into(),
+            Self::ContainsEggs => "Contains Eggs".into(),
+            Self::ContainsShellfish => "Contains Crustacean Shellfish".into(),
+            Self::ContainsFish => "Contains Fish".into(),
+            Self::ContainsSesame => "Contains Sesame".into(),
+            Self::LowCarbon => "Low Carbon Footprint".into(),
+            Self::HighCarbon => "High Carbon Footprint".into(),
+            Self::Other(code) => code.clone(),
+        }
+    }
+
+    pub fn is_allergen(&self) -> bool {
+        matches!(
+            self,
+            Self::ContainsPeanuts
+                | Self::ContainsTreeNuts
+                | Self::ContainsWheat
+                | Self::ContainsGluten
+                | Self::ContainsSoy
+                | Self::ContainsDairy
+                | Self::ContainsEggs
+                | Self::ContainsShellfish
+                | Self::ContainsFish
+                | Self::ContainsSesame
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub enum CarbonFootprint {
+    Low,
+    High,
+    #[default]
+    Unknown,
+}
+
+impl CarbonFootprint {
+    pub fn from_web_codes(codes: &[WebCode]) -> Self {
+        if codes.contains(&WebCode::LowCarbon) {
+            Self::Low
+        } else if codes.contains(&WebCode::HighCarbon) {
+            Self::High
+        } else {
+            Self::Unknown
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Low => "Low".into(),
+            Self::High => "High".into(),
+            Self::Unknown => "Unknown".into(),
+        }
+    }
+}
+
+/// Number of items with each carbon footprint rating in a meal or restaurant menu
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default, Clone, Copy)]
+pub struct CarbonFootprintSummary {
+    pub low: usize,
+    pub high: usize,
+    pub unknown: usize,
+}
+
+impl CarbonFootprintSummary {
+    pub fn add(&mut self, footprint: CarbonFootprint) {
+        match footprint {
+            CarbonFootprint::Low => self.low += 1,
+            CarbonFootprint::High => self.high += 1,
+            CarbonFootprint::Unknown => self.unknown += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &CarbonFootprintSummary) {
+        self.low += other.low;
+        self.high += other.high;
+        self.unknown += other.unknown;
+    }
+
+    pub fn total(&self) -> usize {
+        self.low + self.high + self.unknown
+    }
+
+    /// Share of rated items that have a low carbon footprint, `None` if no item is rated
+    pub fn low_ratio(&self) -> Option<f64> {
+        let rated = self.low + self.high;
+        if rated == 0 {
+            None
+        } else {
+            Some(self.low as f64 / rated as f64)
+        }
+    }
+}
+
+impl From<String> for WebCode {
+    fn from(code: String) -> Self {
+        Self::from_code(&code)
+    }
+}
+
+impl From<WebCode> for String {
+    fn from(code: WebCode) -> Self {
+        code.code()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default, Clone)]
+pub struct NutritionFacts {
+    pub serving_size: Option<String>,
+    pub calories: Option<f64>,
+    pub total_fat: Option<Nutrient>,
+    pub saturated_fat: Option<Nutrient>,
+    pub trans_fat: Option<Nutrient>,
+    pub cholesterol: Option<Nutrient>,
+    pub sodium: Option<Nutrient>,
+    pub total_carbohydrate: Option<Nutrient>,
+    pub dietary_fiber: Option<Nutrient>,
+    pub sugars: Option<Nutrient>,
+    pub protein: Option<Nutrient>,
+    /// Percent daily values for vitamins and minerals
+    pub vitamin_a: Option<u32>,
+    pub vitamin_c: Option<u32>,
+    pub calcium: Option<u32>,
+    pub iron: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Nutrient {
+    pub amount: f64,
+    pub unit: String,
+    /// Percent daily value, if listed on the nutrition label
+    pub daily_value: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, codes: Vec<WebCode>) -> Item {
+        Item {
+            id: id.into(),
+            carbon_footprint: CarbonFootprint::from_web_codes(&codes),
+            web_codes: codes,
+            ..Default::default()
+        }
+    }
+
+    fn meal(name: MealEnum) -> MenuMeal {
+        MenuMeal {
+            name,
+            availability: Availability::Open,
+            hours: None,
+            sections: vec![Section {
+                name: "Flex Bar".into(),
+                items: vec![
+                    item("977026", vec![WebCode::Vegan, WebCode::LowCarbon]),
+                    item("400317", vec![WebCode::ContainsWheat, WebCode::HighCarbon]),
+                    item("977085", vec![]),
+                    item("141301", vec![WebCode::LowCarbon]),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_full_name() {
+        let mut item = item("123056", vec![]);
+        item.name = "Fusilli Fruiti De Mari".into();
+        assert_eq!(item.full_name(), "Fusilli Fruiti De Mari");
+
+        let mut bread = self::item("138012", vec![]);
+        bread.name = "Toasted Herb & Cheese Bread".into();
+        item.accompaniments.push(bread);
+        assert_eq!(
+            item.full_name(),
+            "Fusilli Fruiti De Mari with Toasted Herb & Cheese Bread"
+        );
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(RestaurantEnum::from_name("de neve"), RestaurantEnum::DeNeve);
+        assert_eq!(
+            RestaurantEnum::from_name("HedrickStudy"),
+            RestaurantEnum::TheStudy
+        );
+        assert_eq!(
+            RestaurantEnum::from_name("Sproul"),
+            RestaurantEnum::Other {
+                name: "Sproul".into(),
+                url_name: "Sproul".into(),
+            }
+        );
+        assert_eq!(MealEnum::from_name("dinner"), MealEnum::Dinner);
+        assert_eq!(MealEnum::from_name("Late Night"), MealEnum::LateNight);
+    }
+
+    #[test]
+    fn test_carbon_footprint_from_web_codes() {
+        assert_eq!(
+            CarbonFootprint::from_web_codes(&[WebCode::Vegan, WebCode::LowCarbon]),
+            CarbonFootprint::Low
+        );
+        assert_eq!(
+            CarbonFootprint::from_web_codes(&[WebCode::HighCarbon]),
+            CarbonFootprint::High
+        );
+        assert_eq!(
+            CarbonFootprint::from_web_codes(&[WebCode::ContainsSoy]),
+            CarbonFootprint::Unknown
+        );
+    }
+
+    #[test]
+    fn test_meal_carbon_footprint() {
+        let summary = meal(MealEnum::Lunch).carbon_footprint();
+        assert_eq!(
+            summary,
+            CarbonFootprintSummary {
+                low: 2,
+                high: 1,
+                unknown: 1,
+            }
+        );
+        assert_eq!(summary.total(), 4);
+        assert_eq!(summary.low_ratio(), Some(2.0 / 3.0));
+    }
+
+    #[test]
+    fn test_menu_carbon_footprint() {
+        let menu = Menu {
+            name: RestaurantEnum::DeNeve,
+            meals: vec![meal(MealEnum::Lunch), meal(MealEnum::Dinner)],
+        };
+        assert_eq!(
+            menu.carbon_footprint(),
+            CarbonFootprintSummary {
+                low: 4,
+                high: 2,
+                unknown: 2,
+            }
+        );
+        assert_eq!(CarbonFootprintSummary::default().low_ratio(), None);
+    }
+
+    #[test]
+    fn test_add_restaurant_keeps_closed_and_failed_meals() {
+        let mut menu = DateMenu {
+            date: NaiveDate::from_ymd(2021, 10, 2),
+            restaurants: Vec::new(),
+            calendar: None,
+        };
+        menu.add_restaurant(RestaurantMenu {
+            date: NaiveDate::from_ymd(2021, 10, 2),
+            restaurant: RestaurantEnum::DeNeve,
+            meal: MealEnum::Breakfast,
+            availability: Availability::Closed,
+            sections: Vec::new(),
+        });
+        menu.add_restaurant(RestaurantMenu::failed(
+            NaiveDate::from_ymd(2021, 10, 2),
+            RestaurantEnum::DeNeve,
+            MealEnum::Dinner,
+        ));
+
+        assert_eq!(menu.restaurants.len(), 1);
+        let availability = menu.restaurants[0]
+            .meals
+            .iter()
+            .map(|m| m.availability)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            availability,
+            vec![Availability::Closed, Availability::FetchFailed]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_without_new_fields() {
+        // Meals and items as serialized before availability, hours and web codes were recorded
+        let mut json = serde_json::to_value(meal(MealEnum::Lunch)).unwrap();
+        let meal = json.as_object_mut().unwrap();
+        meal.remove("availability");
+        meal.remove("hours");
+        for item in meal["sections"][0]["items"].as_array_mut().unwrap() {
+            let item = item.as_object_mut().unwrap();
+            for field in &["web_codes", "carbon_footprint", "summary", "accompaniments"] {
+                item.remove(*field);
+            }
+        }
+
+        let meal: MenuMeal = serde_json::from_value(json).unwrap();
+        assert_eq!(meal.availability, Availability::Unknown);
+        assert_eq!(meal.hours, None);
+        let item = &meal.sections[0].items[0];
+        assert!(item.web_codes.is_empty());
+        assert_eq!(item.carbon_footprint, CarbonFootprint::Unknown);
+
+        let details: ItemDetails = serde_json::from_str(
+            r#"{"description": null, "ingredients": null, "allergens": null}"#,
+        )
+        .unwrap();
+        assert_eq!(details, ItemDetails::default());
+    }
 }
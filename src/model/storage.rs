@@ -1,4 +1,8 @@
-use crate::model::{DateMenu, Item, Menu, MenuMeal, RestaurantMenu, Section};
+use crate::calendar::CalendarDay;
+use crate::model::{
+    Availability, CarbonFootprint, CarbonFootprintSummary, DateMenu, Hours, Item, ItemDetails,
+    Menu, MenuMeal, Nutrient, NutritionFacts, RestaurantMenu, Section, WebCode,
+};
 use serde_json::json;
 
 pub trait Storage {
@@ -8,71 +12,291 @@ pub trait Storage {
 
 impl Storage for DateMenu {
     fn to_json(&self) -> serde_json::Value {
-        json!({
+        let mut json = json!({
             "date": self.date,
             "restaurants": self.restaurants.iter().map(|s| s.to_json()).collect::<Vec<serde_json::Value>>(),
-        })
+        });
+        if let Some(calendar) = &self.calendar {
+            json["calendar"] = calendar.to_json();
+        }
+        json
     }
 
     fn to_json_min(&self) -> serde_json::Value {
-        json!([
-            self.date,
-            self.restaurants
+        trim_trailing_empty(vec![
+            json!(self.date),
+            json!(self
+                .restaurants
                 .iter()
                 .map(|s| s.to_json_min())
-                .collect::<Vec<serde_json::Value>>()
+                .collect::<Vec<serde_json::Value>>()),
+            json!(self.calendar.as_ref().map(|c| c.to_json_min())),
         ])
     }
 }
 
-impl Storage for Menu {
+impl Storage for CalendarDay {
     fn to_json(&self) -> serde_json::Value {
         json!({
+            "quarter": self.quarter,
+            "week": self.week,
+            "service": self.service.name(),
+            "note": self.note,
+        })
+    }
+
+    fn to_json_min(&self) -> serde_json::Value {
+        json!([self.quarter, self.week, self.service.name(), self.note])
+    }
+}
+
+impl Storage for Menu {
+    fn to_json(&self) -> serde_json::Value {
+        let mut json = json!({
             "name": self.name.name(),
             "meals": self.meals.iter().map(|s| s.to_json()).collect::<Vec<serde_json::Value>>(),
-        })
+        });
+        if let Some(summary) = carbon_footprint_summary(&self.carbon_footprint()) {
+            json["carbon_footprint"] = summary.to_json();
+        }
+        json
     }
 
     fn to_json_min(&self) -> serde_json::Value {
-        json!([
-            self.name.name(),
-            self.meals
+        trim_trailing_empty(vec![
+            json!(self.name.name()),
+            json!(self
+                .meals
                 .iter()
                 .map(|s| s.to_json_min())
-                .collect::<Vec<serde_json::Value>>()
+                .collect::<Vec<serde_json::Value>>()),
+            json!(carbon_footprint_summary(&self.carbon_footprint()).map(|s| s.to_json_min())),
         ])
     }
 }
 
 impl Storage for MenuMeal {
     fn to_json(&self) -> serde_json::Value {
-        json!({
+        let mut json = json!({
             "name": self.name.name(),
             "sections": self.sections.iter().map(|s| s.to_json()).collect::<Vec<serde_json::Value>>(),
-        })
+        });
+        // Meals are open unless stated otherwise
+        if self.availability != Availability::Open {
+            json["availability"] = json!(self.availability.name());
+        }
+        if let Some(hours) = &self.hours {
+            json["hours"] = hours.to_json();
+        }
+        if let Some(summary) = carbon_footprint_summary(&self.carbon_footprint()) {
+            json["carbon_footprint"] = summary.to_json();
+        }
+        json
     }
 
     fn to_json_min(&self) -> serde_json::Value {
-        json!([
-            self.name.name(),
-            self.sections
+        let availability = match self.availability {
+            Availability::Open => serde_json::Value::Null,
+            _ => json!(self.availability.name()),
+        };
+        trim_trailing_empty(vec![
+            json!(self.name.name()),
+            json!(self
+                .sections
                 .iter()
                 .map(|s| s.to_json_min())
-                .collect::<Vec<serde_json::Value>>()
+                .collect::<Vec<serde_json::Value>>()),
+            availability,
+            json!(self.hours.as_ref().map(|h| h.to_json_min())),
+            json!(carbon_footprint_summary(&self.carbon_footprint()).map(|s| s.to_json_min())),
         ])
     }
 }
 
-impl Storage for Item {
+impl Storage for CarbonFootprintSummary {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "low": self.low,
+            "high": self.high,
+            "unknown": self.unknown,
+        })
+    }
+
+    fn to_json_min(&self) -> serde_json::Value {
+        json!([self.low, self.high, self.unknown])
+    }
+}
+
+/// Carbon footprint counts worth storing, leaving out meals and menus without rated items
+fn carbon_footprint_summary(summary: &CarbonFootprintSummary) -> Option<&CarbonFootprintSummary> {
+    if summary.low + summary.high == 0 {
+        None
+    } else {
+        Some(summary)
+    }
+}
+
+impl Storage for Hours {
     fn to_json(&self) -> serde_json::Value {
         json!({
+            "open": self.open.format("%H:%M").to_string(),
+            "close": self.close.format("%H:%M").to_string(),
+        })
+    }
+
+    fn to_json_min(&self) -> serde_json::Value {
+        json!([
+            self.open.format("%H:%M").to_string(),
+            self.close.format("%H:%M").to_string(),
+        ])
+    }
+}
+
+impl Storage for Item {
+    fn to_json(&self) -> serde_json::Value {
+        let mut json = json!({
             "id": self.id,
             "name": self.name,
+        });
+        if !self.web_codes.is_empty() {
+            json["web_codes"] = web_codes_json(&self.web_codes);
+        }
+        if self.carbon_footprint != CarbonFootprint::Unknown {
+            json["carbon_footprint"] = json!(self.carbon_footprint.name());
+        }
+        if let Some(summary) = &self.summary {
+            json["summary"] = json!(summary);
+        }
+        if !self.accompaniments.is_empty() {
+            json["accompaniments"] = json!(self
+                .accompaniments
+                .iter()
+                .map(|a| a.to_json())
+                .collect::<Vec<serde_json::Value>>());
+        }
+        if let Some(details) = &self.details {
+            json["details"] = details.to_json();
+        }
+        json
+    }
+
+    fn to_json_min(&self) -> serde_json::Value {
+        trim_trailing_empty(vec![
+            json!(self.id),
+            json!(self.name),
+            web_codes_json(&self.web_codes),
+            carbon_footprint_json_min(self.carbon_footprint),
+            json!(self.summary),
+            json!(self
+                .accompaniments
+                .iter()
+                .map(|a| a.to_json_min())
+                .collect::<Vec<serde_json::Value>>()),
+            json!(self.details.as_ref().map(|d| d.to_json_min())),
+        ])
+    }
+}
+
+fn web_codes_json(codes: &[WebCode]) -> serde_json::Value {
+    json!(codes.iter().map(|c| c.code()).collect::<Vec<String>>())
+}
+
+fn carbon_footprint_json_min(footprint: CarbonFootprint) -> serde_json::Value {
+    match footprint {
+        CarbonFootprint::Unknown => serde_json::Value::Null,
+        _ => json!(footprint.name()),
+    }
+}
+
+/// Builds a min JSON array, leaving out trailing optional fields that are null or empty
+fn trim_trailing_empty(mut values: Vec<serde_json::Value>) -> serde_json::Value {
+    while let Some(last) = values.last() {
+        let empty = match last {
+            serde_json::Value::Null => true,
+            serde_json::Value::Array(array) => array.is_empty(),
+            _ => false,
+        };
+        if !empty {
+            break;
+        }
+        values.pop();
+    }
+    serde_json::Value::Array(values)
+}
+
+impl Storage for ItemDetails {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "description": self.description,
+            "ingredients": self.ingredients,
+            "allergens": self.allergens,
+            "nutrition": self.nutrition.as_ref().map(|n| n.to_json()),
         })
     }
 
     fn to_json_min(&self) -> serde_json::Value {
-        json!([self.id, self.name])
+        json!([
+            self.description,
+            self.ingredients,
+            self.allergens,
+            self.nutrition.as_ref().map(|n| n.to_json_min()),
+        ])
+    }
+}
+
+impl Storage for NutritionFacts {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "serving_size": self.serving_size,
+            "calories": self.calories,
+            "total_fat": self.total_fat.as_ref().map(|n| n.to_json()),
+            "saturated_fat": self.saturated_fat.as_ref().map(|n| n.to_json()),
+            "trans_fat": self.trans_fat.as_ref().map(|n| n.to_json()),
+            "cholesterol": self.cholesterol.as_ref().map(|n| n.to_json()),
+            "sodium": self.sodium.as_ref().map(|n| n.to_json()),
+            "total_carbohydrate": self.total_carbohydrate.as_ref().map(|n| n.to_json()),
+            "dietary_fiber": self.dietary_fiber.as_ref().map(|n| n.to_json()),
+            "sugars": self.sugars.as_ref().map(|n| n.to_json()),
+            "protein": self.protein.as_ref().map(|n| n.to_json()),
+            "vitamin_a": self.vitamin_a,
+            "vitamin_c": self.vitamin_c,
+            "calcium": self.calcium,
+            "iron": self.iron,
+        })
+    }
+
+    fn to_json_min(&self) -> serde_json::Value {
+        json!([
+            self.serving_size,
+            self.calories,
+            self.total_fat.as_ref().map(|n| n.to_json_min()),
+            self.saturated_fat.as_ref().map(|n| n.to_json_min()),
+            self.trans_fat.as_ref().map(|n| n.to_json_min()),
+            self.cholesterol.as_ref().map(|n| n.to_json_min()),
+            self.sodium.as_ref().map(|n| n.to_json_min()),
+            self.total_carbohydrate.as_ref().map(|n| n.to_json_min()),
+            self.dietary_fiber.as_ref().map(|n| n.to_json_min()),
+            self.sugars.as_ref().map(|n| n.to_json_min()),
+            self.protein.as_ref().map(|n| n.to_json_min()),
+            self.vitamin_a,
+            self.vitamin_c,
+            self.calcium,
+            self.iron,
+        ])
+    }
+}
+
+impl Storage for Nutrient {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "amount": self.amount,
+            "unit": self.unit,
+            "daily_value": self.daily_value,
+        })
+    }
+
+    fn to_json_min(&self) -> serde_json::Value {
+        json!([self.amount, self.unit, self.daily_value])
     }
 }
 
@@ -97,38 +321,49 @@ impl Storage for Section {
 
 impl Storage for RestaurantMenu {
     fn to_json(&self) -> serde_json::Value {
-        json!({
+        let mut json = json!({
             "date": self.date,
             "name": self.restaurant.name(),
             "meal": self.meal.name(),
             "sections": self.sections.iter().map(|s| s.to_json()).collect::<Vec<serde_json::Value>>(),
-        })
+        });
+        if self.availability != Availability::Open {
+            json["availability"] = json!(self.availability.name());
+        }
+        json
     }
 
     fn to_json_min(&self) -> serde_json::Value {
-        json!([
-            self.date,
-            self.restaurant.name(),
-            self.meal.name(),
-            self.sections
+        let mut json = vec![
+            json!(self.date),
+            json!(self.restaurant.name()),
+            json!(self.meal.name()),
+            json!(self
+                .sections
                 .iter()
                 .map(|s| s.to_json_min())
-                .collect::<Vec<serde_json::Value>>(),
-        ])
+                .collect::<Vec<serde_json::Value>>()),
+        ];
+        if self.availability != Availability::Open {
+            json.push(json!(self.availability.name()));
+        }
+        serde_json::Value::Array(json)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::calendar::Service;
     use crate::model::{DateMenu, MealEnum, Menu, MenuMeal, RestaurantEnum};
+    use chrono::{NaiveDate, NaiveTime};
 
     fn get_test_item() -> Item {
         Item {
             id: "141301".into(),
             name: "Roasted Vegetables".into(),
             recipe_link: "http://menu.dining.ucla.edu/Recipes/141301/2".into(),
-            details: None,
+            ..Default::default()
         }
     }
 
@@ -151,6 +386,167 @@ mod tests {
         );
     }
 
+    fn get_test_item_with_details() -> Item {
+        let mut item = get_test_item();
+        item.summary = Some("Zucchini, Squash, Bell Pepper".into());
+        item.set_details(ItemDetails {
+            description: Some("Seasonal Vegetables".into()),
+            ingredients: Some("Zucchini, Carrot, Olive Oil".into()),
+            allergens: None,
+            web_codes: vec![WebCode::Vegan, WebCode::LowCarbon],
+            nutrition: Some(NutritionFacts {
+                serving_size: Some("4 oz".into()),
+                calories: Some(80.0),
+                total_fat: Some(Nutrient {
+                    amount: 4.5,
+                    unit: "g".into(),
+                    daily_value: Some(7),
+                }),
+                sodium: Some(Nutrient {
+                    amount: 120.0,
+                    unit: "mg".into(),
+                    daily_value: Some(5),
+                }),
+                iron: Some(2),
+                ..Default::default()
+            }),
+        });
+        item
+    }
+
+    #[test]
+    fn test_item_with_summary_json_min() {
+        let mut item = get_test_item();
+        item.summary = Some("(Prepared with Alcohol)".into());
+        assert_eq!(
+            item.to_json_min(),
+            json!([
+                "141301",
+                "Roasted Vegetables",
+                [],
+                null,
+                "(Prepared with Alcohol)"
+            ]),
+        );
+    }
+
+    fn get_test_item_with_accompaniment() -> Item {
+        Item {
+            id: "123056".into(),
+            name: "Fusilli Fruiti De Mari".into(),
+            recipe_link: "http://menu.dining.ucla.edu/Recipes/123056/6".into(),
+            accompaniments: vec![Item {
+                id: "138012".into(),
+                name: "Toasted Herb & Cheese Bread".into(),
+                recipe_link: "http://menu.dining.ucla.edu/Recipes/138012/1".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_item_with_accompaniment_json() {
+        assert_eq!(
+            get_test_item_with_accompaniment().to_json(),
+            json!({
+                "id": "123056",
+                "name": "Fusilli Fruiti De Mari",
+                "accompaniments": [
+                    {"id": "138012", "name": "Toasted Herb & Cheese Bread"},
+                ],
+            }),
+        );
+    }
+
+    #[test]
+    fn test_item_with_accompaniment_json_min() {
+        assert_eq!(
+            get_test_item_with_accompaniment().to_json_min(),
+            json!([
+                "123056",
+                "Fusilli Fruiti De Mari",
+                [],
+                null,
+                null,
+                [["138012", "Toasted Herb & Cheese Bread"]],
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_item_with_details_json() {
+        assert_eq!(
+            get_test_item_with_details().to_json(),
+            json!({
+                "id": "141301",
+                "name": "Roasted Vegetables",
+                "web_codes": ["VG", "LC"],
+                "carbon_footprint": "Low",
+                "summary": "Zucchini, Squash, Bell Pepper",
+                "details": {
+                    "description": "Seasonal Vegetables",
+                    "ingredients": "Zucchini, Carrot, Olive Oil",
+                    "allergens": null,
+                    "nutrition": {
+                        "serving_size": "4 oz",
+                        "calories": 80.0,
+                        "total_fat": {"amount": 4.5, "unit": "g", "daily_value": 7},
+                        "saturated_fat": null,
+                        "trans_fat": null,
+                        "cholesterol": null,
+                        "sodium": {"amount": 120.0, "unit": "mg", "daily_value": 5},
+                        "total_carbohydrate": null,
+                        "dietary_fiber": null,
+                        "sugars": null,
+                        "protein": null,
+                        "vitamin_a": null,
+                        "vitamin_c": null,
+                        "calcium": null,
+                        "iron": 2,
+                    },
+                },
+            }),
+        );
+    }
+
+    #[test]
+    fn test_item_with_details_json_min() {
+        assert_eq!(
+            get_test_item_with_details().to_json_min(),
+            json!([
+                "141301",
+                "Roasted Vegetables",
+                ["VG", "LC"],
+                "Low",
+                "Zucchini, Squash, Bell Pepper",
+                [],
+                [
+                    "Seasonal Vegetables",
+                    "Zucchini, Carrot, Olive Oil",
+                    null,
+                    [
+                        "4 oz",
+                        80.0,
+                        [4.5, "g", 7],
+                        null,
+                        null,
+                        null,
+                        [120.0, "mg", 5],
+                        null,
+                        null,
+                        null,
+                        null,
+                        null,
+                        null,
+                        null,
+                        2,
+                    ],
+                ],
+            ]),
+        );
+    }
+
     fn get_test_section() -> Section {
         Section {
             name: "The Front Burner".into(),
@@ -159,19 +555,19 @@ mod tests {
                     id: "123056".into(),
                     name: "Fusilli Fruiti De Mari".into(),
                     recipe_link: "http://menu.dining.ucla.edu/Recipes/123056/6".into(),
-                    details: None,
+                    ..Default::default()
                 },
                 Item {
                     id: "138012".into(),
                     name: "Toasted Herb & Cheese Bread".into(),
                     recipe_link: "http://menu.dining.ucla.edu/Recipes/138012/1".into(),
-                    details: None,
+                    ..Default::default()
                 },
                 Item {
                     id: "141301".into(),
                     name: "Roasted Vegetables".into(),
                     recipe_link: "http://menu.dining.ucla.edu/Recipes/141301/2".into(),
-                    details: None,
+                    ..Default::default()
                 },
             ],
         }
@@ -216,13 +612,13 @@ mod tests {
                         id: "977026".into(),
                         name: "Italian Minestrone Soup".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/977026/6".into(),
-                        details: None,
+                        ..Default::default()
                     },
                     Item {
                         id: "977085".into(),
                         name: "Turkey & Rice Soup".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/977085/6".into(),
-                        details: None,
+                        ..Default::default()
                     },
                 ],
             },
@@ -233,19 +629,19 @@ mod tests {
                         id: "123056".into(),
                         name: "Fusilli Fruiti De Mari".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/123056/6".into(),
-                        details: None,
+                        ..Default::default()
                     },
                     Item {
                         id: "138012".into(),
                         name: "Toasted Herb & Cheese Bread".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/138012/1".into(),
-                        details: None,
+                        ..Default::default()
                     },
                     Item {
                         id: "141301".into(),
                         name: "Roasted Vegetables".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/141301/2".into(),
-                        details: None,
+                        ..Default::default()
                     },
                 ],
             },
@@ -254,9 +650,10 @@ mod tests {
 
     fn get_test_menu() -> RestaurantMenu {
         RestaurantMenu {
-            date: "2021-09-30".into(),
+            date: NaiveDate::from_ymd(2021, 9, 30),
             restaurant: RestaurantEnum::DeNeve,
             meal: MealEnum::Lunch,
+            availability: Availability::Open,
             sections: get_test_sections(),
         }
     }
@@ -322,6 +719,8 @@ mod tests {
     fn get_test_meal() -> MenuMeal {
         MenuMeal {
             name: MealEnum::Lunch,
+            availability: Availability::Open,
+            hours: None,
             sections: get_test_sections(),
         }
     }
@@ -380,16 +779,97 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_meal_hours_json() {
+        let meal = MenuMeal {
+            hours: Some(Hours {
+                open: NaiveTime::from_hms(17, 0, 0),
+                close: NaiveTime::from_hms(21, 0, 0),
+            }),
+            ..get_test_meal()
+        };
+        assert_eq!(
+            meal.to_json()["hours"],
+            json!({"open": "17:00", "close": "21:00"})
+        );
+        assert_eq!(meal.to_json_min()[2], serde_json::Value::Null);
+        assert_eq!(meal.to_json_min()[3], json!(["17:00", "21:00"]));
+    }
+
+    #[test]
+    fn test_carbon_footprint_summary_json() {
+        let item = |id: &str, carbon_footprint| Item {
+            id: id.into(),
+            carbon_footprint,
+            ..Default::default()
+        };
+        let meal = MenuMeal {
+            sections: vec![Section {
+                name: "Grill".into(),
+                items: vec![
+                    item("1", CarbonFootprint::Low),
+                    item("2", CarbonFootprint::Low),
+                    item("3", CarbonFootprint::High),
+                ],
+            }],
+            ..get_test_meal()
+        };
+        assert_eq!(
+            meal.to_json()["carbon_footprint"],
+            json!({"low": 2, "high": 1, "unknown": 0})
+        );
+        assert_eq!(meal.to_json_min()[4], json!([2, 1, 0]));
+
+        let menu = Menu {
+            name: RestaurantEnum::DeNeve,
+            meals: vec![meal, get_test_meal()],
+        };
+        assert_eq!(
+            menu.to_json()["carbon_footprint"],
+            json!({"low": 2, "high": 1, "unknown": 5})
+        );
+        assert_eq!(menu.to_json_min()[2], json!([2, 1, 5]));
+    }
+
+    #[test]
+    fn test_closed_meal_json() {
+        let meal = MenuMeal {
+            name: MealEnum::Breakfast,
+            availability: Availability::Closed,
+            hours: None,
+            sections: Vec::new(),
+        };
+        assert_eq!(
+            meal.to_json(),
+            json!({"name": "Breakfast", "sections": [], "availability": "Closed"}),
+        );
+        assert_eq!(meal.to_json_min(), json!(["Breakfast", [], "Closed"]));
+
+        let menu = RestaurantMenu::failed(
+            NaiveDate::from_ymd(2021, 9, 30),
+            RestaurantEnum::DeNeve,
+            MealEnum::Dinner,
+        );
+        assert_eq!(
+            menu.to_json_min(),
+            json!(["2021-09-30", "De Neve", "Dinner", [], "Fetch Failed"]),
+        );
+    }
+
     fn get_test_menu_full() -> Menu {
         Menu {
             name: RestaurantEnum::DeNeve,
             meals: vec![
                 MenuMeal {
                     name: MealEnum::Lunch,
+                    availability: Availability::Open,
+                    hours: None,
                     sections: get_test_sections(),
                 },
                 MenuMeal {
                     name: MealEnum::Dinner,
+                    availability: Availability::Open,
+                    hours: None,
                     sections: get_test_sections(),
                 },
             ],
@@ -443,7 +923,7 @@ mod tests {
                             },
                         ],
                     },
-                ]
+                ],
             }),
         )
     }
@@ -495,24 +975,28 @@ mod tests {
                             ],
                         ],
                     ],
-                ]
+                ],
             ]),
         )
     }
 
     fn get_test_date_menu() -> DateMenu {
         DateMenu {
-            date: "2021-10-08".to_string(),
+            date: NaiveDate::from_ymd(2021, 10, 8),
             restaurants: vec![
                 Menu {
                     name: RestaurantEnum::DeNeve,
                     meals: vec![
                         MenuMeal {
                             name: MealEnum::Lunch,
+                            availability: Availability::Open,
+                            hours: None,
                             sections: get_test_sections(),
                         },
                         MenuMeal {
                             name: MealEnum::Dinner,
+                            availability: Availability::Open,
+                            hours: None,
                             sections: get_test_sections(),
                         },
                     ],
@@ -522,15 +1006,20 @@ mod tests {
                     meals: vec![
                         MenuMeal {
                             name: MealEnum::Breakfast,
+                            availability: Availability::Open,
+                            hours: None,
                             sections: get_test_sections(),
                         },
                         MenuMeal {
                             name: MealEnum::Lunch,
+                            availability: Availability::Open,
+                            hours: None,
                             sections: get_test_sections(),
                         },
                     ],
                 },
             ],
+            calendar: None,
         }
     }
 
@@ -584,7 +1073,7 @@ mod tests {
                                     },
                                 ],
                             },
-                        ]
+                        ],
                     },
                     {
                         "name": "Bruin Plate",
@@ -629,7 +1118,7 @@ mod tests {
                                     },
                                 ],
                             },
-                        ]
+                        ],
                     }
                 ]
             }),
@@ -686,7 +1175,7 @@ mod tests {
                                     ],
                                 ],
                             ],
-                        ]
+                        ],
                     ],
                     [
                         "Bruin Plate",
@@ -731,10 +1220,41 @@ mod tests {
                                     ],
                                 ],
                             ],
-                        ]
+                        ],
                     ]
                 ]
             ]),
         )
     }
+
+    #[test]
+    fn test_date_menu_calendar_json() {
+        let menu = DateMenu {
+            date: NaiveDate::from_ymd(2021, 11, 25),
+            restaurants: Vec::new(),
+            calendar: Some(CalendarDay {
+                quarter: Some("Fall 2021".into()),
+                week: Some(9),
+                service: Service::Closed,
+                note: Some("Thanksgiving".into()),
+            }),
+        };
+        assert_eq!(
+            menu.to_json(),
+            json!({
+                "date": "2021-11-25",
+                "restaurants": [],
+                "calendar": {
+                    "quarter": "Fall 2021",
+                    "week": 9,
+                    "service": "Closed",
+                    "note": "Thanksgiving",
+                },
+            })
+        );
+        assert_eq!(
+            menu.to_json_min(),
+            json!(["2021-11-25", [], ["Fall 2021", 9, "Closed", "Thanksgiving"]])
+        );
+    }
 }
@@ -1,2 +1,195 @@
+pub mod parse_hours;
 pub mod parse_item;
+pub mod parse_meals;
 pub mod parse_menu;
+pub mod parse_restaurants;
+
+use crate::model::WebCode;
+use crate::request::default_base_url;
+use itertools::Itertools;
+use scraper::element_ref::ElementRef;
+use scraper::{Html, Selector};
+use url::Url;
+
+/// Part of a page that could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// An element required by the page layout is missing
+    MissingElement { element: String, context: String },
+    /// A link could not be resolved, or does not point where it should
+    InvalidLink { link: String, reason: String },
+    /// A value such as a nutrient amount is not in the expected format
+    InvalidValue { field: String, value: String },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingElement { element, context } => {
+                write!(f, "missing {} in \"{}\"", element, context)
+            }
+            ParseError::InvalidLink { link, reason } => {
+                write!(f, "invalid link {}: {}", link, reason)
+            }
+            ParseError::InvalidValue { field, value } => {
+                write!(f, "invalid {} \"{}\"", field, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Everything that could be parsed from a page, along with what could not
+#[derive(Debug, PartialEq)]
+pub struct Parsed<T> {
+    pub value: T,
+    pub diagnostics: Vec<ParseError>,
+}
+
+impl<T> Parsed<T> {
+    /// Convert the parsed value, keeping its diagnostics
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Parsed<U> {
+        Parsed {
+            value: f(self.value),
+            diagnostics: self.diagnostics,
+        }
+    }
+}
+
+/// Collects the web codes from all `webcode-16px` icons under an element, in order of appearance
+fn parse_web_codes(element: &ElementRef) -> Vec<WebCode> {
+    let mut codes = Vec::new();
+    for img in element.select(&Selector::parse("img.webcode-16px").unwrap()) {
+        if let Some(alt) = img.value().attr("alt") {
+            let code = WebCode::from_code(alt);
+            if !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+    }
+    codes
+}
+
+/// Text of an element with all whitespace collapsed, to identify it in diagnostics
+fn element_context(element: &ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .join(" ")
+}
+
+/// Path segments of a link below the base url of the site, or nothing for links elsewhere.
+///
+/// Absolute links to the upstream site, as left in pages served by a mirror, are taken as links
+/// to the same path below the base url.
+fn site_path_segments<'a>(url: &'a Url, base: &Url) -> Option<Vec<&'a str>> {
+    let upstream = default_base_url();
+    let path = if url.origin() == base.origin() {
+        url.path().strip_prefix(base.path())?
+    } else if url.origin() == upstream.origin() {
+        url.path().strip_prefix(upstream.path())?
+    } else {
+        return None;
+    };
+    Some(path.split('/').collect())
+}
+
+/// Collects the slugs `slug` extracts from the site path segments of the links on the page at the
+/// url, along with their link text as display name. Each slug is kept once, in order of
+/// appearance. Links that can't be resolved are reported.
+fn parse_slug_links(
+    doc: &str,
+    page: &str,
+    base: &Url,
+    slug: impl Fn(&[&str]) -> Option<String>,
+) -> Parsed<Vec<(String, String)>> {
+    let mut links: Vec<(String, String)> = Vec::new();
+    let mut diagnostics = Vec::new();
+    let page = match Url::parse(page) {
+        Ok(page) => page,
+        Err(error) => {
+            diagnostics.push(ParseError::InvalidLink {
+                link: page.into(),
+                reason: error.to_string(),
+            });
+            return Parsed {
+                value: links,
+                diagnostics,
+            };
+        }
+    };
+    for link in Html::parse_document(doc).select(&Selector::parse("a").unwrap()) {
+        let href = match link.value().attr("href") {
+            Some(href) => href,
+            None => continue,
+        };
+        let url = match page.join(href) {
+            Ok(url) => url,
+            Err(error) => {
+                diagnostics.push(ParseError::InvalidLink {
+                    link: href.into(),
+                    reason: error.to_string(),
+                });
+                continue;
+            }
+        };
+        let found = match site_path_segments(&url, base).and_then(|segments| slug(&segments)) {
+            Some(found) => found,
+            None => continue,
+        };
+        if links.iter().any(|(known, _)| *known == found) {
+            continue;
+        }
+        let name = element_context(&link);
+        let name = if name.is_empty() { found.clone() } else { name };
+        links.push((found, name));
+    }
+    Parsed {
+        value: links,
+        diagnostics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::parse_base_url;
+
+    #[test]
+    fn test_parse_slug_links() {
+        let html = r#"
+<a href="http://menu.dining.ucla.edu/Menus/BruinPlate">Bruin Plate</a>
+<a href="/mirror/Menus/DeNeve"> De
+  Neve </a>
+<a href="Menus/FeastAtRieber"></a>
+<a href="/Menus/Covel">Covel</a>
+<a href="https://dining.ucla.edu/Menus/Elsewhere">Elsewhere</a>
+<a href="http://menu.dining.ucla.edu/Hours">Hours</a>
+<a href="http://menu.dining.ucla.edu/Menus/BruinPlate/2021-10-02">Bruin Plate today</a>
+<a name="top">Anchors without a target are skipped</a>
+<a href="http://[::1/Menus/Broken">Broken</a>
+        "#;
+        // A mirror serves the upstream pages unmodified, so absolute links still point upstream
+        let base = parse_base_url("http://localhost:8080/mirror/").unwrap();
+        let links = parse_slug_links(html, base.as_str(), &base, |segments| match segments {
+            ["Menus", slug, ..] => Some(slug.to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            links.value,
+            vec![
+                ("BruinPlate".to_string(), "Bruin Plate".to_string()),
+                ("DeNeve".to_string(), "De Neve".to_string()),
+                ("FeastAtRieber".to_string(), "FeastAtRieber".to_string()),
+            ]
+        );
+        assert_eq!(links.diagnostics.len(), 1);
+
+        // Nothing can be resolved against a page that isn't a url
+        let links = parse_slug_links(html, "mirror", &base, |_| None);
+        assert!(links.value.is_empty());
+        assert_eq!(links.diagnostics.len(), 1);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::model::{Hours, MealEnum, MealHours, RestaurantEnum};
+use crate::parse::{element_context, site_path_segments, ParseError, Parsed};
+use crate::request::hours::HoursRequest;
+use crate::request::Downloadable;
+use chrono::NaiveTime;
+use scraper::element_ref::ElementRef;
+use scraper::{Html, Selector};
+use url::Url;
+
+/// Parses the hours table for the open and close times of every meal served at every restaurant.
+/// Meals a restaurant doesn't serve that day are left out.
+pub fn parse(doc: &str, request: &HoursRequest) -> Parsed<Vec<MealHours>> {
+    let doc = Html::parse_document(doc);
+    let mut diagnostics = Vec::new();
+    let mut hours = Vec::new();
+
+    let table_selector = Selector::parse("table.hours-table").unwrap();
+    let cell_selector = Selector::parse("td").unwrap();
+    let range_selector = Selector::parse("span.hours-range").unwrap();
+    for table in doc.select(&table_selector) {
+        let meals = table
+            .select(&Selector::parse("th").unwrap())
+            .skip(1)
+            .map(|th| {
+                let name = element_context(&th);
+                MealEnum::from_url_name(&name.replace(' ', ""), &name)
+            })
+            .collect::<Vec<MealEnum>>();
+
+        for row in table.select(&Selector::parse("tr").unwrap()) {
+            let mut cells = row.select(&cell_selector);
+            let restaurant = match cells.next() {
+                Some(head) => parse_restaurant(&head, request),
+                None => continue,
+            };
+            for (cell, meal) in cells.zip(&meals) {
+                let range = match cell.select(&range_selector).next() {
+                    Some(range) => element_context(&range),
+                    None => continue,
+                };
+                match parse_range(&range) {
+                    Some(meal_hours) => hours.push(MealHours {
+                        restaurant: restaurant.clone(),
+                        meal: meal.clone(),
+                        hours: meal_hours,
+                    }),
+                    None => diagnostics.push(ParseError::InvalidValue {
+                        field: format!("{} {} hours", restaurant.name(), meal.name()),
+                        value: range,
+                    }),
+                }
+            }
+        }
+    }
+
+    Parsed {
+        value: hours,
+        diagnostics,
+    }
+}
+
+/// Identifies the restaurant of a row by its menu link, or by its name when it has none
+fn parse_restaurant(head: &ElementRef, request: &HoursRequest) -> RestaurantEnum {
+    let name = element_context(head);
+    let base = Url::parse(&request.url()).ok();
+    let url_name = head
+        .select(&Selector::parse("a[href]").unwrap())
+        .filter_map(|a| base.as_ref()?.join(a.value().attr("href")?).ok())
+        .find_map(
+            |url| match site_path_segments(&url, &request.base)?.as_slice() {
+                ["Menus", slug, ..] if !slug.is_empty() => Some(slug.to_string()),
+                _ => None,
+            },
+        );
+    match url_name {
+        Some(url_name) => RestaurantEnum::from_url_name(&url_name, &name),
+        None => RestaurantEnum::well_known()
+            .into_iter()
+            .find(|r| r.name() == name)
+            .unwrap_or_else(|| RestaurantEnum::from_url_name(&name.replace(' ', ""), &name)),
+    }
+}
+
+/// Parses a range such as "7:00 a.m. - 10:00 a.m."
+fn parse_range(range: &str) -> Option<Hours> {
+    let (open, close) = range.split_once(['-', '–'])?;
+    Some(Hours {
+        open: parse_time(open)?,
+        close: parse_time(close)?,
+    })
+}
+
+/// Parses a 12-hour clock time such as "7:00 a.m.", "12 p.m." or "9:30PM"
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    let text = text.to_lowercase().replace(['.', ' '], "");
+    let (time, pm) = match text.strip_suffix("pm") {
+        Some(time) => (time, true),
+        None => (text.strip_suffix("am")?, false),
+    };
+    let (hour, minute) = match time.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse().ok()?),
+        None => (time.parse::<u32>().ok()?, 0),
+    };
+    if hour == 0 || hour > 12 {
+        return None;
+    }
+    NaiveTime::from_hms_opt(hour % 12 + if pm { 12 } else { 0 }, minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::default_base_url;
+    use chrono::NaiveDate;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("7:00 a.m."), Some(time(7, 0)));
+        assert_eq!(parse_time(" 12:00 p.m."), Some(time(12, 0)));
+        assert_eq!(parse_time("12 a.m."), Some(time(0, 0)));
+        assert_eq!(parse_time("9:30PM"), Some(time(21, 30)));
+        assert_eq!(parse_time("noon"), None);
+        assert_eq!(parse_time("13:00 p.m."), None);
+    }
+
+    #[test]
+    fn test_parse_hours() {
+        let html = r#"
+<table class="hours-table">
+  <thead>
+    <tr>
+      <th class="hours-head">Location</th>
+      <th class="hours-head">Breakfast</th>
+      <th class="hours-head">Lunch</th>
+      <th class="hours-head">Dinner</th>
+      <th class="hours-head">Late Night</th>
+    </tr>
+  </thead>
+  <tbody>
+    <tr>
+      <td class="hours-head"><span class="hours-location"><a href="/Menus/DeNeve">De Neve</a></span></td>
+      <td class="hours-open Breakfast"><span class="hours-range">7:00 a.m. - 10:00 a.m.</span></td>
+      <td class="hours-open Lunch"><span class="hours-range">11:00 a.m. - 3:00 p.m.</span></td>
+      <td class="hours-open Dinner"><span class="hours-range">5:00 p.m. - 9:00 p.m.</span></td>
+      <td class="hours-open LateNight"><span class="hours-range">9:00 p.m. - 12:00 a.m.</span></td>
+    </tr>
+    <tr>
+      <td class="hours-head"><span class="hours-location">Bruin Plate</span></td>
+      <td class="hours-closed Breakfast"><span class="hours-closed">CLOSED</span></td>
+      <td class="hours-open Lunch"><span class="hours-range">11:00 a.m. - 3:00 p.m.</span></td>
+      <td class="hours-open Dinner"><span class="hours-range">TBA</span></td>
+      <td class="hours-closed LateNight"><span class="hours-closed">CLOSED</span></td>
+    </tr>
+  </tbody>
+</table>
+        "#;
+        let request = HoursRequest::new(default_base_url(), NaiveDate::from_ymd(2021, 10, 2));
+        let parsed = parse(html, &request);
+
+        let hours = |restaurant, meal, open, close| MealHours {
+            restaurant,
+            meal,
+            hours: Hours { open, close },
+        };
+        assert_eq!(
+            parsed.value,
+            vec![
+                hours(
+                    RestaurantEnum::DeNeve,
+                    MealEnum::Breakfast,
+                    time(7, 0),
+                    time(10, 0)
+                ),
+                hours(
+                    RestaurantEnum::DeNeve,
+                    MealEnum::Lunch,
+                    time(11, 0),
+                    time(15, 0)
+                ),
+                hours(
+                    RestaurantEnum::DeNeve,
+                    MealEnum::Dinner,
+                    time(17, 0),
+                    time(21, 0)
+                ),
+                hours(
+                    RestaurantEnum::DeNeve,
+                    MealEnum::LateNight,
+                    time(21, 0),
+                    time(0, 0)
+                ),
+                hours(
+                    RestaurantEnum::BruinPlate,
+                    MealEnum::Lunch,
+                    time(11, 0),
+                    time(15, 0)
+                ),
+            ]
+        );
+        assert_eq!(
+            parsed.diagnostics,
+            vec![ParseError::InvalidValue {
+                field: "Bruin Plate Dinner hours".into(),
+                value: "TBA".into(),
+            }]
+        );
+    }
+}
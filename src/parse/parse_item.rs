@@ -1,28 +1,47 @@
-use crate::model::ItemDetails;
-use scraper::{Html, Selector};
+use crate::model::{ItemDetails, Nutrient, NutritionFacts, WebCode};
+use crate::parse::{element_context, parse_web_codes, ParseError, Parsed};
+use scraper::element_ref::ElementRef;
+use scraper::{Html, Node, Selector};
 
-pub fn parse(doc: &str) -> ItemDetails {
-    parse_item(&Html::parse_document(doc))
+/// Parses a recipe page, keeping every detail that could be parsed
+pub fn parse(doc: &str) -> Parsed<ItemDetails> {
+    let mut diagnostics = Vec::new();
+    let value = parse_item(&Html::parse_document(doc), &mut diagnostics);
+    Parsed { value, diagnostics }
 }
 
-fn parse_item(doc: &Html) -> ItemDetails {
+fn parse_item(doc: &Html, diagnostics: &mut Vec<ParseError>) -> ItemDetails {
+    if doc
+        .select(&Selector::parse("div.productinfo").unwrap())
+        .next()
+        .is_none()
+    {
+        diagnostics.push(ParseError::MissingElement {
+            element: "product info".into(),
+            context: doc
+                .select(&Selector::parse("h2").unwrap())
+                .next()
+                .map(|title| element_context(&title))
+                .unwrap_or_default(),
+        });
+    }
     ItemDetails {
         description: parse_description(doc),
         ingredients: parse_ingredients(doc),
         allergens: parse_allergens(doc),
+        web_codes: parse_prod_web_codes(doc),
+        nutrition: parse_nutrition(doc, diagnostics),
     }
 }
 
 fn parse_description(doc: &Html) -> Option<String> {
     Some(
         doc.select(&Selector::parse("div").unwrap())
-            .filter(|e| e.value().attr("class") == Some("productinfo"))
-            .nth(0)?
+            .find(|e| e.value().attr("class") == Some("productinfo"))?
             .select(&Selector::parse("div").unwrap())
-            .filter(|e| e.value().attr("class") == Some("description"))
-            .nth(0)?
+            .find(|e| e.value().attr("class") == Some("description"))?
             .text()
-            .nth(0)?
+            .next()?
             .trim()
             .into(),
     )
@@ -31,13 +50,12 @@ fn parse_description(doc: &Html) -> Option<String> {
 fn parse_ingredients(doc: &Html) -> Option<String> {
     Some(
         doc.select(&Selector::parse("div").unwrap())
-            .filter(|e| {
+            .find(|e| {
                 if let Some(cls) = e.value().attr("class") {
                     return cls.contains("ingred_allergen");
                 }
-                return false;
-            })
-            .nth(0)?
+                false
+            })?
             .text()
             .nth(2)?
             .trim()
@@ -48,13 +66,12 @@ fn parse_ingredients(doc: &Html) -> Option<String> {
 fn parse_allergens(doc: &Html) -> Option<String> {
     Some(
         doc.select(&Selector::parse("div").unwrap())
-            .filter(|e| {
+            .find(|e| {
                 if let Some(cls) = e.value().attr("class") {
                     return cls.contains("ingred_allergen");
                 }
-                return false;
-            })
-            .nth(0)?
+                false
+            })?
             .text()
             .nth(5)?
             .trim()
@@ -62,6 +79,148 @@ fn parse_allergens(doc: &Html) -> Option<String> {
     )
 }
 
+fn parse_prod_web_codes(doc: &Html) -> Vec<WebCode> {
+    doc.select(&Selector::parse("div.prodwebcode").unwrap())
+        .flat_map(|e| parse_web_codes(&e))
+        .collect()
+}
+
+fn parse_nutrition(doc: &Html, diagnostics: &mut Vec<ParseError>) -> Option<NutritionFacts> {
+    let nfbox = doc.select(&Selector::parse("div.nfbox").unwrap()).next()?;
+
+    let mut facts = NutritionFacts {
+        serving_size: parse_serving_size(&nfbox),
+        calories: parse_calories(&nfbox),
+        ..Default::default()
+    };
+
+    for nutrient in nfbox.select(&Selector::parse("p.nfnutrient").unwrap()) {
+        let (name, value) = match parse_nutrient(&nutrient) {
+            Some(parsed) => parsed,
+            None => {
+                diagnostics.push(ParseError::InvalidValue {
+                    field: "nutrient".into(),
+                    value: element_context(&nutrient),
+                });
+                continue;
+            }
+        };
+        let field = match name.as_str() {
+            "Total Fat" => &mut facts.total_fat,
+            "Saturated Fat" => &mut facts.saturated_fat,
+            "Trans Fat" => &mut facts.trans_fat,
+            "Cholesterol" => &mut facts.cholesterol,
+            "Sodium" => &mut facts.sodium,
+            "Total Carbohydrate" => &mut facts.total_carbohydrate,
+            "Dietary Fiber" => &mut facts.dietary_fiber,
+            "Sugars" => &mut facts.sugars,
+            "Protein" => &mut facts.protein,
+            _ => continue,
+        };
+        *field = Some(value);
+    }
+
+    for vitamin in nfbox.select(&Selector::parse("span.nfvitname").unwrap()) {
+        let name = vitamin.text().collect::<String>();
+        let percent = vitamin
+            .next_siblings()
+            .filter_map(ElementRef::wrap)
+            .find(|e| e.value().attr("class") == Some("nfvitpct"))
+            .map(|e| e.text().collect::<String>());
+        let percent = match percent {
+            Some(text) => match parse_percent(&text) {
+                Some(percent) => Some(percent),
+                None => {
+                    diagnostics.push(ParseError::InvalidValue {
+                        field: name.trim().into(),
+                        value: text.trim().into(),
+                    });
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let field = match name.trim() {
+            "Vitamin A" => &mut facts.vitamin_a,
+            "Vitamin C" => &mut facts.vitamin_c,
+            "Calcium" => &mut facts.calcium,
+            "Iron" => &mut facts.iron,
+            _ => continue,
+        };
+        *field = percent;
+    }
+
+    Some(facts)
+}
+
+fn parse_serving_size(nfbox: &ElementRef) -> Option<String> {
+    let text = nfbox
+        .select(&Selector::parse("p.nfserv").unwrap())
+        .next()?
+        .text()
+        .collect::<String>()
+        .replace('\u{a0}', " ");
+    let size = text.trim().trim_start_matches("Serving Size").trim();
+    if size.is_empty() {
+        None
+    } else {
+        Some(size.into())
+    }
+}
+
+fn parse_calories(nfbox: &ElementRef) -> Option<f64> {
+    nfbox
+        .select(&Selector::parse("p.nfcal").unwrap())
+        .next()?
+        .text()
+        .collect::<String>()
+        .trim()
+        .trim_start_matches("Calories")
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Parses a nutrient line such as "Total Fat 21.3g 33%" into its name and value
+fn parse_nutrient(nutrient: &ElementRef) -> Option<(String, Nutrient)> {
+    // The daily value lives in its own span, everything else is "<name> <amount><unit>"
+    let label = nutrient
+        .children()
+        .filter_map(|child| match child.value() {
+            Node::Text(text) => Some(text.to_string()),
+            Node::Element(e) if e.attr("class") != Some("nfdvval") => {
+                ElementRef::wrap(child).map(|e| e.text().collect::<String>())
+            }
+            _ => None,
+        })
+        .collect::<String>()
+        .replace('\u{a0}', " ");
+
+    let (name, quantity) = label.trim().rsplit_once(' ')?;
+    let split = quantity
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(quantity.len());
+    let (amount, unit) = quantity.split_at(split);
+
+    let daily_value = nutrient
+        .select(&Selector::parse("span.nfdvvalnum").unwrap())
+        .next()
+        .and_then(|e| e.text().collect::<String>().trim().parse().ok());
+
+    Some((
+        name.trim().into(),
+        Nutrient {
+            amount: amount.parse().ok()?,
+            unit: unit.into(),
+            daily_value,
+        },
+    ))
+}
+
+fn parse_percent(text: &str) -> Option<u32> {
+    text.trim().trim_end_matches('%').trim().parse().ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,13 +293,89 @@ mod tests {
 <img alt="Bruin Cheeseburger" class="recipeimage" src="./bruin-cheeseburger_files/400317.jpg">
 </div>
         "#;
-        let doc = scraper::Html::parse_document(html);
-        let parsed = parse_item(&doc);
+        let parsed = parse(html);
+        assert_eq!(parsed.diagnostics, vec![]);
         let expected = ItemDetails {
             description: Some("Blended Patty, American Cheese, Lettuce, Tomato, Pickle, Red Onion, Mayo, House-made Bun".into()),
             ingredients: Some("Blended Burger Patty (Halal Ground Beef, Onion, Roasted Mushroom, Quinoa, Garlic Salt, Pepper), Vegan Hamburger Bun (Water, Flour, Whole Wheat Flour, Vital Wheat Gluten, Sugar, Canola Oil, Sea Salt, Yeast), Tomato, American Cheese, Red Onion, Green Leaf Lettuce, Pickles, Butter, Mayonnaise, Kosher Salt, Pepper".into()),
             allergens: Some("Milk, Eggs, Wheat, Soybeans, Gluten".into()),
+            web_codes: vec![
+                WebCode::ContainsWheat,
+                WebCode::ContainsGluten,
+                WebCode::ContainsSoy,
+                WebCode::ContainsDairy,
+                WebCode::ContainsEggs,
+                WebCode::HighCarbon,
+            ],
+            nutrition: Some(NutritionFacts {
+                serving_size: Some("1 each".into()),
+                calories: Some(459.0),
+                total_fat: Some(Nutrient { amount: 21.3, unit: "g".into(), daily_value: Some(33) }),
+                saturated_fat: Some(Nutrient { amount: 8.8, unit: "g".into(), daily_value: Some(44) }),
+                trans_fat: Some(Nutrient { amount: 1.1, unit: "g".into(), daily_value: None }),
+                cholesterol: Some(Nutrient { amount: 65.7, unit: "mg".into(), daily_value: Some(22) }),
+                sodium: Some(Nutrient { amount: 1014.9, unit: "mg".into(), daily_value: Some(42) }),
+                total_carbohydrate: Some(Nutrient { amount: 43.9, unit: "g".into(), daily_value: Some(34) }),
+                dietary_fiber: Some(Nutrient { amount: 1.6, unit: "g".into(), daily_value: Some(6) }),
+                sugars: Some(Nutrient { amount: 4.3, unit: "g".into(), daily_value: None }),
+                protein: Some(Nutrient { amount: 21.7, unit: "g".into(), daily_value: None }),
+                vitamin_a: Some(11),
+                vitamin_c: Some(13),
+                calcium: Some(18),
+                iron: Some(30),
+            }),
         };
-        assert_eq!(parsed, expected);
+        assert_eq!(parsed.value, expected);
+    }
+
+    #[test]
+    fn test_parse_item_without_nutrition() {
+        let html = r#"
+<div class="recipecontainer">
+<h2>Fresh Fruit</h2>
+<div class="productinfo">
+    <div class="description">Seasonal Fruit</div>
+</div>
+</div>
+        "#;
+        let parsed = parse(html);
+        assert_eq!(parsed.value.nutrition, None);
+        assert_eq!(parsed.diagnostics, vec![]);
+    }
+
+    #[test]
+    fn test_parse_item_keeps_valid_nutrients() {
+        let html = r#"
+<div class="recipecontainer">
+<h2>Fresh Fruit</h2>
+<div class="nfbox">
+    <p class="nfnutrient"><span class="nfmaintext">Total Fat</span> 0.3g<span class="nfdvval"><span class="nfdvvalnum">0</span>%</span></p>
+    <p class="nfnutrient"><span class="nfmaintext">Sodium</span> n/a</p>
+    <span class="nfvitname">Iron</span><span class="nfvitpct">--</span>
+</div>
+</div>
+        "#;
+        let parsed = parse(html);
+        let nutrition = parsed.value.nutrition.unwrap();
+        assert_eq!(nutrition.total_fat.unwrap().amount, 0.3);
+        assert_eq!(nutrition.sodium, None);
+        assert_eq!(nutrition.iron, None);
+        assert_eq!(
+            parsed.diagnostics,
+            vec![
+                ParseError::MissingElement {
+                    element: "product info".into(),
+                    context: "Fresh Fruit".into(),
+                },
+                ParseError::InvalidValue {
+                    field: "nutrient".into(),
+                    value: "Sodium n/a".into(),
+                },
+                ParseError::InvalidValue {
+                    field: "Iron".into(),
+                    value: "--".into(),
+                },
+            ]
+        );
     }
 }
@@ -0,0 +1,73 @@
+use crate::model::MealEnum;
+use crate::parse::{parse_slug_links, Parsed};
+use crate::request::menu::MealsRequest;
+use crate::request::Downloadable;
+
+/// Parses the meal tabs of a restaurant's page for all meals served on the requested date
+pub fn parse(doc: &str, request: &MealsRequest) -> Parsed<Vec<MealEnum>> {
+    parse_slug_links(doc, &request.url(), &request.base, |segments| {
+        parse_url_name(segments, request)
+    })
+    .map(|links| {
+        links
+            .iter()
+            .map(|(url_name, name)| MealEnum::from_url_name(url_name, name))
+            .collect()
+    })
+}
+
+/// Extracts the meal slug from links like `/Menus/DeNeve/2021-10-02/Brunch`
+fn parse_url_name(segments: &[&str], request: &MealsRequest) -> Option<String> {
+    match segments {
+        ["Menus", restaurant, date, meal]
+            if *restaurant == request.restaurant.url_name()
+                && *date == request.date.to_string()
+                && !meal.is_empty() =>
+        {
+            Some(meal.to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::RestaurantEnum;
+    use crate::request::default_base_url;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_meals() {
+        let html = r#"
+<div class="meal-tabs">
+  <a href="http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-02/Brunch">Brunch</a>
+  <a href="/Menus/DeNeve/2021-10-02/Dinner">Dinner</a>
+  <a href="2021-10-02/LateNight">Late Night</a>
+  <a href="http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-02/Midnight">Midnight Snack</a>
+  <a href="http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-02/Brunch">Brunch</a>
+</div>
+<div class="other-days">
+  <a href="http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-03/Breakfast">Breakfast</a>
+  <a href="http://menu.dining.ucla.edu/Menus/BruinPlate/2021-10-02/Lunch">Lunch</a>
+</div>
+        "#;
+        let request = MealsRequest::new(
+            default_base_url(),
+            NaiveDate::from_ymd(2021, 10, 2),
+            RestaurantEnum::DeNeve,
+        );
+        assert_eq!(
+            parse(html, &request).value,
+            vec![
+                MealEnum::Brunch,
+                MealEnum::Dinner,
+                MealEnum::LateNight,
+                MealEnum::Other {
+                    name: "Midnight Snack".into(),
+                    url_name: "Midnight".into(),
+                },
+            ]
+        );
+    }
+}
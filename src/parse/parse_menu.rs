@@ -1,88 +1,382 @@
-use crate::model::{Item, RestaurantMenu, Section};
+use crate::model::{Availability, CarbonFootprint, Item, RestaurantMenu, Section};
+use crate::parse::{element_context, parse_web_codes, site_path_segments, ParseError, Parsed};
 use crate::request::menu::MenuRequest;
+use crate::request::Downloadable;
+use itertools::Itertools;
 use scraper::element_ref::ElementRef;
 use scraper::{Html, Selector};
 use url::Url;
 
-pub fn parse(doc: &str, request: &MenuRequest) -> RestaurantMenu {
-    let sections = parse_sections(&Html::parse_document(doc));
-    RestaurantMenu {
-        date: request.date.clone(),
-        restaurant: request.restaurant.clone(),
-        meal: request.meal.clone(),
-        sections: sections,
+/// Parses a menu page, keeping every section and item that could be parsed
+pub fn parse(doc: &str, request: &MenuRequest) -> Parsed<RestaurantMenu> {
+    let mut diagnostics = Vec::new();
+    let doc = Html::parse_document(doc);
+    let sections = parse_sections(&doc, request, &mut diagnostics);
+    let availability = parse_availability(&doc, &sections, &mut diagnostics);
+    Parsed {
+        value: RestaurantMenu {
+            date: request.date,
+            restaurant: request.restaurant.clone(),
+            meal: request.meal.clone(),
+            availability,
+            sections,
+        },
+        diagnostics,
     }
 }
 
-fn parse_sections(doc: &Html) -> Vec<Section> {
-    doc.select(&Selector::parse("li").unwrap())
-        .filter(|e| e.value().attr("class") == Some("sect-item"))
-        .map(|section| Section {
-            name: parse_section_name(&section),
-            items: parse_section_items(&section),
-        })
-        .collect()
+/// A meal without sections is closed when the menu block carries a notice saying so, like
+/// `<p>This dining hall is closed for breakfast today.</p>`. Otherwise the markup may have changed,
+/// so the availability is unknown and the page is reported.
+fn parse_availability(
+    doc: &Html,
+    sections: &[Section],
+    diagnostics: &mut Vec<ParseError>,
+) -> Availability {
+    if !sections.is_empty() {
+        return Availability::Open;
+    }
+    let notices = ["closed", "no menu", "not available"];
+    let closed = doc
+        .select(&Selector::parse("div.menu-block > p").unwrap())
+        .map(|notice| element_context(&notice).to_lowercase())
+        .any(|text| notices.iter().any(|notice| text.contains(notice)));
+    if closed {
+        return Availability::Closed;
+    }
+    diagnostics.push(ParseError::MissingElement {
+        element: "menu sections or closed notice".into(),
+        context: element_context(&doc.root_element())
+            .chars()
+            .take(80)
+            .collect(),
+    });
+    Availability::Unknown
 }
 
-fn parse_section_name(section: &ElementRef) -> String {
-    section.text().nth(0).unwrap().trim().into()
+fn parse_sections(
+    doc: &Html,
+    request: &MenuRequest,
+    diagnostics: &mut Vec<ParseError>,
+) -> Vec<Section> {
+    let mut sections = Vec::new();
+    for section in doc
+        .select(&Selector::parse("li").unwrap())
+        .filter(|e| e.value().attr("class") == Some("sect-item"))
+    {
+        match parse_section_name(&section) {
+            Ok(name) => sections.push(Section {
+                name,
+                items: parse_section_items(&section, request, diagnostics),
+            }),
+            Err(error) => diagnostics.push(error),
+        }
+    }
+    sections
 }
 
-fn parse_section_items(section: &ElementRef) -> Vec<Item> {
+fn parse_section_name(section: &ElementRef) -> Result<String, ParseError> {
     section
+        .text()
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(String::from)
+        .ok_or_else(|| ParseError::MissingElement {
+            element: "section name".into(),
+            context: element_context(section),
+        })
+}
+
+fn parse_section_items(
+    section: &ElementRef,
+    request: &MenuRequest,
+    diagnostics: &mut Vec<ParseError>,
+) -> Vec<Item> {
+    let mut items: Vec<Item> = Vec::new();
+    for node in section
         .select(&Selector::parse("li").unwrap())
         .filter(|e| e.value().attr("class") == Some("menu-item"))
-        .map(|item| parse_item(&item))
-        .collect()
+    {
+        let item = match parse_item(&node, request) {
+            Ok(item) => item,
+            Err(error) => {
+                diagnostics.push(error);
+                continue;
+            }
+        };
+        // A "w/" item is served with the dish listed right before it
+        match items.last_mut() {
+            Some(previous) if is_accompaniment(&node) => previous.accompaniments.push(item),
+            _ => items.push(item),
+        }
+    }
+    items
+}
+
+fn is_accompaniment(item: &ElementRef) -> bool {
+    let text = item
+        .select(&Selector::parse("span.tooltip-target-wrapper").unwrap())
+        .next()
+        .and_then(|wrapper| wrapper.text().next());
+    matches!(text, Some(text) if text.trim().starts_with("w/"))
 }
 
-fn parse_item(item: &ElementRef) -> Item {
+fn parse_item(item: &ElementRef, request: &MenuRequest) -> Result<Item, ParseError> {
+    let missing = |element: &str| ParseError::MissingElement {
+        element: element.into(),
+        context: element_context(item),
+    };
     let node = item
         .select(&Selector::parse("a").unwrap())
-        .filter(|e| e.value().attr("class") == Some("recipelink"))
-        .nth(0)
-        .unwrap();
+        .find(|e| e.value().attr("class") == Some("recipelink"))
+        .ok_or_else(|| missing("recipe link"))?;
 
-    let recipe_link = parse_item_recipe_link(&node);
+    let recipe_link = parse_item_recipe_link(&node, request).ok_or_else(|| missing("href"))??;
+    let web_codes = parse_web_codes(item);
 
-    Item {
-        id: parse_id(&recipe_link),
-        name: parse_item_name(&node),
-        recipe_link: recipe_link,
+    Ok(Item {
+        id: parse_id(&recipe_link, &request.base)?,
+        name: parse_item_name(&node).ok_or_else(|| missing("item name"))?,
+        recipe_link: recipe_link.into(),
+        carbon_footprint: CarbonFootprint::from_web_codes(&web_codes),
+        web_codes,
+        summary: parse_item_summary(item),
+        accompaniments: Vec::new(),
         details: None,
-    }
+    })
 }
 
-fn parse_item_name(item: &ElementRef) -> String {
-    item.text().nth(0).unwrap().into()
+fn parse_item_name(item: &ElementRef) -> Option<String> {
+    item.text().next().map(String::from)
 }
 
-fn parse_item_recipe_link(item: &ElementRef) -> String {
-    item.value().attr("href").unwrap().into()
+fn parse_item_summary(item: &ElementRef) -> Option<String> {
+    let summary = item
+        .select(&Selector::parse("div.tt-description").unwrap())
+        .next()?
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .join(" ");
+    if summary.is_empty() {
+        None
+    } else {
+        Some(summary)
+    }
 }
 
-fn parse_id(recipe_link: &String) -> String {
-    let parsed_url = Url::parse(recipe_link.as_str()).unwrap();
-    return parsed_url.path_segments().unwrap().nth(1).unwrap().into();
+/// Resolves the recipe link against the menu page, which may link to it relatively.
+/// Returns nothing when the anchor has no href.
+fn parse_item_recipe_link(
+    item: &ElementRef,
+    request: &MenuRequest,
+) -> Option<Result<Url, ParseError>> {
+    let href = item.value().attr("href")?;
+    let invalid = |error: url::ParseError| ParseError::InvalidLink {
+        link: href.into(),
+        reason: error.to_string(),
+    };
+    Some(
+        Url::parse(&request.url())
+            .and_then(|page| page.join(href))
+            .map_err(invalid),
+    )
+}
+
+/// Extracts the recipe id from links like `/Recipes/977026/6` below the base url
+fn parse_id(recipe_link: &Url, base: &Url) -> Result<String, ParseError> {
+    match site_path_segments(recipe_link, base).as_deref() {
+        Some(["Recipes", id, ..]) if !id.is_empty() => Ok(id.to_string()),
+        _ => Err(ParseError::InvalidLink {
+            link: recipe_link.to_string(),
+            reason: format!("not a recipe link below {}", base),
+        }),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::{MealEnum, RestaurantEnum, WebCode};
+    use crate::request::{default_base_url, parse_base_url};
+    use chrono::NaiveDate;
+
+    fn request(base: Url) -> MenuRequest {
+        MenuRequest::new(
+            base,
+            NaiveDate::from_ymd(2021, 10, 2),
+            RestaurantEnum::DeNeve,
+            MealEnum::Dinner,
+        )
+    }
+
+    fn link(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
 
     #[test]
     fn test_parse_id() {
+        let base = default_base_url();
+        assert_eq!(
+            parse_id(&link("http://menu.dining.ucla.edu/Recipes/977026/6"), &base),
+            Ok("977026".to_string()),
+        );
         assert_eq!(
-            parse_id(&"http://menu.dining.ucla.edu/Recipes/977026/6".into()),
-            "977026".to_string(),
+            parse_id(&link("http://menu.dining.ucla.edu/Recipes/977085/6"), &base),
+            Ok("977085".to_string()),
         );
         assert_eq!(
-            parse_id(&"http://menu.dining.ucla.edu/Recipes/977085/6".into()),
-            "977085".to_string(),
+            parse_id(&link("http://menu.dining.ucla.edu/Recipes/141301/2"), &base),
+            Ok("141301".to_string()),
+        );
+
+        let mirror = parse_base_url("http://localhost:8080/mirror").unwrap();
+        assert_eq!(
+            parse_id(
+                &link("http://localhost:8080/mirror/Recipes/141301/2"),
+                &mirror
+            ),
+            Ok("141301".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_parse_relative_recipe_links() {
+        let html = r#"
+<ul class="sect-list">
+  <li class="sect-item">
+    Grill
+    <ul class="item-list">
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">
+          <a class="recipelink" href="/mirror/Recipes/977026/6">Italian Minestrone Soup</a>
+        </span>
+      </li>
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">
+          <a class="recipelink" href="../../../Recipes/977085/6">Turkey &amp; Rice Soup</a>
+        </span>
+      </li>
+    </ul>
+  </li>
+</ul>
+        "#;
+        let menu = parse(
+            html,
+            &request(parse_base_url("http://localhost:8080/mirror/").unwrap()),
+        )
+        .value;
+        let items = &menu.sections[0].items;
+        assert_eq!(items[0].id, "977026");
+        assert_eq!(
+            items[0].recipe_link,
+            "http://localhost:8080/mirror/Recipes/977026/6"
         );
+        assert_eq!(items[1].id, "977085");
         assert_eq!(
-            parse_id(&"http://menu.dining.ucla.edu/Recipes/141301/2".into()),
-            "141301".to_string(),
+            items[1].recipe_link,
+            "http://localhost:8080/mirror/Recipes/977085/6"
+        );
+    }
+
+    #[test]
+    fn test_parse_mirrored_upstream_links() {
+        let html = r#"
+<ul class="sect-list">
+  <li class="sect-item">
+    Grill
+    <ul class="item-list">
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">
+          <a class="recipelink" href="http://menu.dining.ucla.edu/Recipes/977026/6">Italian Minestrone Soup</a>
+        </span>
+      </li>
+    </ul>
+  </li>
+</ul>
+        "#;
+        let parsed = parse(
+            html,
+            &request(parse_base_url("http://localhost:8080/mirror/").unwrap()),
+        );
+        assert_eq!(parsed.diagnostics, vec![]);
+        let items = &parsed.value.sections[0].items;
+        assert_eq!(items[0].id, "977026");
+        assert_eq!(
+            items[0].recipe_link,
+            "http://menu.dining.ucla.edu/Recipes/977026/6"
+        );
+    }
+
+    #[test]
+    fn test_parse_closed_meal() {
+        let html = r#"
+<div class="menu-block">
+  <h3 class="col-header">Breakfast</h3>
+  <p>This dining hall is closed for breakfast today.</p>
+</div>
+        "#;
+        let parsed = parse(html, &request(default_base_url()));
+        assert_eq!(parsed.value.availability, Availability::Closed);
+        assert!(parsed.value.sections.is_empty());
+        assert_eq!(parsed.diagnostics, vec![]);
+    }
+
+    #[test]
+    fn test_parse_unknown_availability() {
+        // Mentions of closing elsewhere on the page are no closed notice
+        let html = r#"
+<div class="menu-block">
+  <h3 class="col-header">Breakfast</h3>
+</div>
+<div id="footer">Dining halls are closed on university holidays.</div>
+        "#;
+        let parsed = parse(html, &request(default_base_url()));
+        assert_eq!(parsed.value.availability, Availability::Unknown);
+        assert!(parsed.value.sections.is_empty());
+        assert_eq!(parsed.diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_keeps_valid_items() {
+        let html = r#"
+<ul class="sect-list">
+  <li class="sect-item">
+    Grill
+    <ul class="item-list">
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">Sold Out</span>
+      </li>
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">
+          <a class="recipelink" href="http://menu.dining.ucla.edu/Hours">Hours</a>
+        </span>
+      </li>
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">
+          <a class="recipelink" href="http://menu.dining.ucla.edu/Recipes/977085/6">Turkey &amp; Rice Soup</a>
+        </span>
+      </li>
+    </ul>
+  </li>
+</ul>
+        "#;
+        let parsed = parse(html, &request(default_base_url()));
+        let items = &parsed.value.sections[0].items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "977085");
+        assert_eq!(
+            parsed.diagnostics,
+            vec![
+                ParseError::MissingElement {
+                    element: "recipe link".into(),
+                    context: "Sold Out".into(),
+                },
+                ParseError::InvalidLink {
+                    link: "http://menu.dining.ucla.edu/Hours".into(),
+                    reason: "not a recipe link below http://menu.dining.ucla.edu/".into(),
+                },
+            ]
         );
     }
 
@@ -185,7 +479,7 @@ mod tests {
 </ul>
         "#;
         let doc = scraper::Html::parse_document(html);
-        let parsed_sections = parse_sections(&doc);
+        let parsed_sections = parse_sections(&doc, &request(default_base_url()), &mut Vec::new());
         let expected_sections = vec![
             Section {
                 name: "Flex Bar".into(),
@@ -194,12 +488,26 @@ mod tests {
                         id: "977026".into(),
                         name: "Italian Minestrone Soup".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/977026/6".into(),
+                        web_codes: vec![
+                            WebCode::Vegan,
+                            WebCode::ContainsWheat,
+                            WebCode::ContainsGluten,
+                            WebCode::ContainsSoy,
+                            WebCode::LowCarbon,
+                        ],
+                        carbon_footprint: CarbonFootprint::Low,
+                        summary: Some("Tomato, Onion, Celery, Carrot, Spinach, Chickpea, Kidney Bean, Pasta, Basil, Oregano".into()),
+                        accompaniments: vec![],
                         details: None,
                     },
                     Item {
                         id: "977085".into(),
                         name: "Turkey & Rice Soup".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/977085/6".into(),
+                        web_codes: vec![],
+                        carbon_footprint: CarbonFootprint::Unknown,
+                        summary: None,
+                        accompaniments: vec![],
                         details: None,
                     },
                 ],
@@ -211,18 +519,42 @@ mod tests {
                         id: "123056".into(),
                         name: "Fusilli Fruiti De Mari".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/123056/6".into(),
-                        details: None,
-                    },
-                    Item {
-                        id: "138012".into(),
-                        name: "Toasted Herb & Cheese Bread".into(),
-                        recipe_link: "http://menu.dining.ucla.edu/Recipes/138012/1".into(),
+                        web_codes: vec![
+                            WebCode::ContainsWheat,
+                            WebCode::ContainsGluten,
+                            WebCode::ContainsSoy,
+                            WebCode::ContainsDairy,
+                            WebCode::ContainsEggs,
+                            WebCode::ContainsShellfish,
+                            WebCode::ContainsFish,
+                        ],
+                        carbon_footprint: CarbonFootprint::Unknown,
+                        summary: Some("(Prepared with Alcohol)".into()),
+                        accompaniments: vec![Item {
+                            id: "138012".into(),
+                            name: "Toasted Herb & Cheese Bread".into(),
+                            recipe_link: "http://menu.dining.ucla.edu/Recipes/138012/1".into(),
+                            web_codes: vec![
+                                WebCode::Vegetarian,
+                                WebCode::ContainsWheat,
+                                WebCode::ContainsGluten,
+                                WebCode::ContainsDairy,
+                            ],
+                            carbon_footprint: CarbonFootprint::Unknown,
+                            summary: None,
+                            accompaniments: vec![],
+                            details: None,
+                        }],
                         details: None,
                     },
                     Item {
                         id: "141301".into(),
                         name: "Roasted Vegetables".into(),
                         recipe_link: "http://menu.dining.ucla.edu/Recipes/141301/2".into(),
+                        web_codes: vec![WebCode::Vegan, WebCode::LowCarbon],
+                        carbon_footprint: CarbonFootprint::Low,
+                        summary: None,
+                        accompaniments: vec![],
                         details: None,
                     },
                 ],
@@ -230,4 +562,30 @@ mod tests {
         ];
         assert_eq!(parsed_sections, expected_sections);
     }
+
+    #[test]
+    fn test_parse_unknown_web_code() {
+        let html = r#"
+<ul class="sect-list">
+  <li class="sect-item">
+    Grill
+    <ul class="item-list">
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">
+          <a class="recipelink" href="http://menu.dining.ucla.edu/Recipes/400317/1">Bruin Cheeseburger</a>
+          <img alt="AWHT" class="webcode-16px" src="/Content/Images/WebCodes/128px/awht.png" />
+          <img alt="XYZ" class="webcode-16px" src="/Content/Images/WebCodes/128px/xyz.png" />
+        </span>
+      </li>
+    </ul>
+  </li>
+</ul>
+        "#;
+        let doc = scraper::Html::parse_document(html);
+        let sections = parse_sections(&doc, &request(default_base_url()), &mut Vec::new());
+        assert_eq!(
+            sections[0].items[0].web_codes,
+            vec![WebCode::ContainsWheat, WebCode::Other("XYZ".into())]
+        );
+    }
 }
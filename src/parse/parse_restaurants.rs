@@ -0,0 +1,64 @@
+use crate::model::RestaurantEnum;
+use crate::parse::{parse_slug_links, Parsed};
+use crate::request::restaurants::RestaurantsRequest;
+use crate::request::Downloadable;
+
+/// Parses the dining site landing page for all restaurants that have a menu page
+pub fn parse(doc: &str, request: &RestaurantsRequest) -> Parsed<Vec<RestaurantEnum>> {
+    parse_slug_links(doc, &request.url(), &request.base, parse_url_name).map(|links| {
+        links
+            .iter()
+            .map(|(url_name, name)| RestaurantEnum::from_url_name(url_name, name))
+            .collect()
+    })
+}
+
+/// Extracts the restaurant slug from links like `/Menus/DeNeve` or `/Menus/DeNeve/2021-09-28`
+fn parse_url_name(segments: &[&str]) -> Option<String> {
+    match segments {
+        ["Menus", slug, ..] if !slug.is_empty() && *slug != "Today" => Some(slug.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::default_base_url;
+
+    #[test]
+    fn test_parse_restaurants() {
+        let html = r#"
+<div id="main-nav">
+  <a href="http://menu.dining.ucla.edu/Menus">Menus</a>
+  <a href="http://menu.dining.ucla.edu/Hours">Hours</a>
+</div>
+<div class="menu-block">
+  <h3 class="col-header"><a href="http://menu.dining.ucla.edu/Menus/BruinPlate">Bruin Plate</a></h3>
+  <a href="http://menu.dining.ucla.edu/Menus/BruinPlate/2021-09-28/Breakfast">Breakfast</a>
+</div>
+<div class="menu-block">
+  <h3 class="col-header"><a href="/Menus/DeNeve">De Neve</a></h3>
+</div>
+<div class="menu-block">
+  <h3 class="col-header"><a href="Menus/FeastAtRieber">Feast at Rieber</a></h3>
+</div>
+<div class="menu-block">
+  <h3 class="col-header"><a href="http://menu.dining.ucla.edu/Menus/SpiceKitchen">Spice Kitchen</a></h3>
+</div>
+<a href="https://dining.ucla.edu/Menus/Elsewhere">Elsewhere</a>
+        "#;
+        assert_eq!(
+            parse(html, &RestaurantsRequest::new(default_base_url())).value,
+            vec![
+                RestaurantEnum::BruinPlate,
+                RestaurantEnum::DeNeve,
+                RestaurantEnum::FeastAtRieber,
+                RestaurantEnum::Other {
+                    name: "Spice Kitchen".into(),
+                    url_name: "SpiceKitchen".into(),
+                },
+            ]
+        );
+    }
+}
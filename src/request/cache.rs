@@ -0,0 +1,391 @@
+use super::cassette::cassette_path;
+use super::transport::{Transport, NOT_MODIFIED};
+use crate::fs::temp_path;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directives of a Cache-Control header that matter to the cache
+#[derive(Debug, Default, PartialEq)]
+pub struct CacheControl {
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub max_age: Option<u64>,
+}
+
+impl CacheControl {
+    pub fn parse(header: &str) -> Self {
+        let mut control = CacheControl::default();
+        for directive in header.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+            match directive.split_once('=') {
+                Some(("max-age", seconds)) => {
+                    control.max_age = seconds.trim_matches('"').parse().ok()
+                }
+                None if directive == "no-store" => control.no_store = true,
+                None if directive == "no-cache" => control.no_cache = true,
+                _ => (),
+            }
+        }
+        control
+    }
+}
+
+/// A cached page along with the validators needed to revalidate it
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct CacheEntry {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Seconds since the unix epoch at which the page was downloaded or last revalidated
+    pub stored_at: u64,
+    /// How many seconds the page may be served without revalidating it
+    pub max_age: Option<u64>,
+    #[serde(skip)]
+    pub body: String,
+}
+
+impl CacheEntry {
+    fn from_headers(url: &str, headers: &HeaderMap, body: String) -> Self {
+        let mut entry = CacheEntry {
+            url: url.into(),
+            body,
+            ..Default::default()
+        };
+        entry.update(headers);
+        entry
+    }
+
+    /// Refresh validators and freshness from the headers of a new or revalidated response
+    fn update(&mut self, headers: &HeaderMap) {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(String::from)
+        };
+        if let Some(etag) = header(header::ETAG) {
+            self.etag = Some(etag);
+        }
+        if let Some(last_modified) = header(header::LAST_MODIFIED) {
+            self.last_modified = Some(last_modified);
+        }
+        let control = header(header::CACHE_CONTROL)
+            .map(|value| CacheControl::parse(&value))
+            .unwrap_or_default();
+        self.max_age = if control.no_cache {
+            Some(0)
+        } else {
+            control.max_age
+        };
+        self.stored_at = now();
+    }
+
+    pub fn is_fresh(&self, now: u64) -> bool {
+        match self.max_age {
+            Some(max_age) => now < self.stored_at.saturating_add(max_age),
+            None => false,
+        }
+    }
+
+    /// Headers turning a request for the page into a conditional one
+    pub fn conditional_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let etag = self
+            .etag
+            .as_deref()
+            .and_then(|v| HeaderValue::from_str(v).ok());
+        if let Some(etag) = etag {
+            headers.insert(header::IF_NONE_MATCH, etag);
+        }
+        let last_modified = self
+            .last_modified
+            .as_deref()
+            .and_then(|v| HeaderValue::from_str(v).ok());
+        if let Some(last_modified) = last_modified {
+            headers.insert(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+
+    pub async fn load(dir: &Path, url: &str) -> Option<Self> {
+        let path = cassette_path(dir, url);
+        let meta = tokio::fs::read_to_string(path.with_extension("json"))
+            .await
+            .ok()?;
+        let mut entry: CacheEntry = serde_json::from_str(&meta).ok()?;
+        entry.body = tokio::fs::read_to_string(path).await.ok()?;
+        Some(entry)
+    }
+
+    /// Store the page and its metadata. Each file is replaced atomically, the page first, so that
+    /// an interrupted save never leaves a truncated page behind.
+    pub async fn save(&self, dir: &Path) -> Result<()> {
+        let path = cassette_path(dir, &self.url);
+        let meta_path = path.with_extension("json");
+        let meta = serde_json::to_string(self).map_err(|e| Error::serialization(&meta_path, e))?;
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| Error::io(dir, e))?;
+        write_atomic(&path, &self.body).await?;
+        write_atomic(&meta_path, &meta).await
+    }
+}
+
+/// Write a file through a temporary file next to it, which is then renamed over the original
+async fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let temp = temp_path(path);
+    tokio::fs::write(&temp, contents)
+        .await
+        .map_err(|e| Error::io(&temp, e))?;
+    tokio::fs::rename(&temp, path)
+        .await
+        .map_err(|e| Error::io(path, e))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Downloads pages with another transport through an on-disk HTTP cache.
+///
+/// Fresh pages are served from disk, stale ones are revalidated with conditional requests, and
+/// everything is downloaded again when forcing a refresh.
+#[derive(Debug)]
+pub struct CachingTransport<T> {
+    inner: T,
+    dir: PathBuf,
+    force_refresh: bool,
+}
+
+impl<T: Transport> CachingTransport<T> {
+    pub fn new(inner: T, dir: PathBuf) -> Self {
+        CachingTransport {
+            inner,
+            dir,
+            force_refresh: false,
+        }
+    }
+
+    /// Ignore cached pages and download everything again, storing the new copies
+    pub fn force_refresh(mut self, force_refresh: bool) -> Self {
+        self.force_refresh = force_refresh;
+        self
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for CachingTransport<T> {
+    async fn get(&self, url: &str) -> Result<String> {
+        let cached = if self.force_refresh {
+            None
+        } else {
+            CacheEntry::load(&self.dir, url).await
+        };
+        if let Some(entry) = &cached {
+            if entry.is_fresh(now()) {
+                return Ok(entry.body.clone());
+            }
+        }
+
+        let conditional = cached
+            .as_ref()
+            .map(CacheEntry::conditional_headers)
+            .unwrap_or_default();
+        let response = self.inner.fetch(url, conditional).await?;
+
+        if response.status == NOT_MODIFIED {
+            let mut entry = cached.ok_or_else(|| Error::status(url, NOT_MODIFIED))?;
+            entry.update(&response.headers);
+            entry.save(&self.dir).await?;
+            return Ok(entry.body);
+        }
+
+        let no_store = matches!(
+            response.headers.get(header::CACHE_CONTROL).map(|value| value.to_str()),
+            Some(Ok(value)) if CacheControl::parse(value).no_store
+        );
+        if !no_store {
+            CacheEntry::from_headers(url, &response.headers, response.body.clone())
+                .save(&self.dir)
+                .await?;
+        }
+        Ok(response.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::transport::MemoryTransport;
+
+    #[test]
+    fn test_parse_cache_control() {
+        assert_eq!(
+            CacheControl::parse("public, max-age=900"),
+            CacheControl {
+                max_age: Some(900),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            CacheControl::parse("no-cache, No-Store"),
+            CacheControl {
+                no_store: true,
+                no_cache: true,
+                max_age: None,
+            }
+        );
+        assert_eq!(CacheControl::parse("private"), CacheControl::default());
+    }
+
+    #[test]
+    fn test_entry_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, HeaderValue::from_static("\"abc\""));
+        headers.insert(
+            header::LAST_MODIFIED,
+            HeaderValue::from_static("Sat, 02 Oct 2021 07:00:00 GMT"),
+        );
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("max-age=60"),
+        );
+        let entry =
+            CacheEntry::from_headers("http://menu.dining.ucla.edu/Menus", &headers, "".into());
+
+        assert_eq!(entry.etag.as_deref(), Some("\"abc\""));
+        assert!(entry.is_fresh(entry.stored_at + 59));
+        assert!(!entry.is_fresh(entry.stored_at + 60));
+
+        let conditional = entry.conditional_headers();
+        assert_eq!(conditional[header::IF_NONE_MATCH], "\"abc\"");
+        assert_eq!(
+            conditional[header::IF_MODIFIED_SINCE],
+            "Sat, 02 Oct 2021 07:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn test_entry_without_max_age_is_stale() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-cache, max-age=60"),
+        );
+        let entry =
+            CacheEntry::from_headers("http://menu.dining.ucla.edu/Menus", &headers, "".into());
+        assert!(!entry.is_fresh(entry.stored_at));
+        assert!(!CacheEntry::default().is_fresh(0));
+    }
+
+    #[tokio::test]
+    async fn test_entry_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = CacheEntry {
+            url: "http://menu.dining.ucla.edu/Recipes/123056/1".into(),
+            etag: Some("W/\"1\"".into()),
+            last_modified: None,
+            stored_at: 1633158000,
+            max_age: Some(900),
+            body: "<p>Fusilli</p>".into(),
+        };
+        entry.save(dir.path()).await.unwrap();
+        let loaded = CacheEntry::load(dir.path(), &entry.url).await;
+
+        assert_eq!(loaded, Some(entry));
+    }
+
+    const URL: &str = "http://menu.dining.ucla.edu/Recipes/123056/1";
+
+    fn entry(body: &str, max_age: Option<u64>) -> CacheEntry {
+        CacheEntry {
+            url: URL.into(),
+            etag: Some("\"v1\"".into()),
+            last_modified: None,
+            stored_at: now(),
+            max_age,
+            body: body.into(),
+        }
+    }
+
+    fn page(cache_control: &'static str) -> MemoryTransport {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, HeaderValue::from_static("\"v1\""));
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(cache_control),
+        );
+        let mut memory = MemoryTransport::new();
+        memory.insert_with_headers(URL.into(), "<p>Downloaded</p>".into(), headers);
+        memory
+    }
+
+    #[tokio::test]
+    async fn test_serves_fresh_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        entry("<p>Cached</p>", Some(900))
+            .save(dir.path())
+            .await
+            .unwrap();
+
+        let cache = CachingTransport::new(page("max-age=900"), dir.path().into());
+        let body = cache.get(URL).await;
+
+        assert_eq!(body.unwrap(), "<p>Cached</p>");
+        assert_eq!(cache.inner.requested(), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn test_revalidates_stale_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        entry("<p>Cached</p>", Some(0))
+            .save(dir.path())
+            .await
+            .unwrap();
+
+        // The page still has the same ETag, so the server answers 304 Not Modified
+        let cache = CachingTransport::new(page("max-age=900"), dir.path().into());
+        let body = cache.get(URL).await;
+        let stored = CacheEntry::load(dir.path(), URL).await.unwrap();
+
+        assert_eq!(body.unwrap(), "<p>Cached</p>");
+        assert_eq!(cache.inner.requested(), vec![URL]);
+        assert_eq!(stored.max_age, Some(900));
+        assert_eq!(stored.body, "<p>Cached</p>");
+    }
+
+    #[tokio::test]
+    async fn test_no_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CachingTransport::new(page("no-store"), dir.path().into());
+        let body = cache.get(URL).await;
+        let stored = CacheEntry::load(dir.path(), URL).await;
+
+        assert_eq!(body.unwrap(), "<p>Downloaded</p>");
+        assert_eq!(stored, None);
+    }
+
+    #[tokio::test]
+    async fn test_force_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        entry("<p>Cached</p>", Some(900))
+            .save(dir.path())
+            .await
+            .unwrap();
+
+        let cache =
+            CachingTransport::new(page("max-age=60"), dir.path().into()).force_refresh(true);
+        let body = cache.get(URL).await;
+        let stored = CacheEntry::load(dir.path(), URL).await.unwrap();
+
+        assert_eq!(body.unwrap(), "<p>Downloaded</p>");
+        assert_eq!(stored.body, "<p>Downloaded</p>");
+        assert_eq!(stored.max_age, Some(60));
+    }
+}
@@ -0,0 +1,161 @@
+use super::transport::Transport;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+/// File in a cassette directory holding the page downloaded from a url.
+///
+/// Characters other than letters, digits, `-` and `.` are escaped as `_` followed by their hex
+/// bytes, so distinct urls never share a file.
+pub fn cassette_path(dir: &Path, url: &str) -> PathBuf {
+    let mut name = String::with_capacity(url.len());
+    for byte in url.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' => name.push(byte as char),
+            _ => name.push_str(&format!("_{:02X}", byte)),
+        }
+    }
+    dir.join(format!("{}.html", name))
+}
+
+/// File next to a cassette page recording the HTTP status the download failed with
+fn status_path(dir: &Path, url: &str) -> PathBuf {
+    cassette_path(dir, url).with_extension("status")
+}
+
+/// Downloads pages with another transport and stores a copy of each in a cassette directory
+#[derive(Debug)]
+pub struct RecordingTransport<T> {
+    inner: T,
+    dir: PathBuf,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    pub fn new(inner: T, dir: PathBuf) -> Self {
+        RecordingTransport { inner, dir }
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for RecordingTransport<T> {
+    async fn get(&self, url: &str) -> Result<String> {
+        let result = self.inner.get(url).await;
+        // Only pages and failed statuses can be replayed, other errors are not recorded
+        let (path, contents, stale) = match &result {
+            Ok(body) => (
+                cassette_path(&self.dir, url),
+                body.clone(),
+                status_path(&self.dir, url),
+            ),
+            Err(Error::Status { status, .. }) => (
+                status_path(&self.dir, url),
+                status.to_string(),
+                cassette_path(&self.dir, url),
+            ),
+            Err(_) => return result,
+        };
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| Error::io(&self.dir, e))?;
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|e| Error::io(&path, e))?;
+        // Drop the outcome of an earlier recording, which would otherwise take precedence
+        match tokio::fs::remove_file(&stale).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(Error::io(&stale, e)),
+            _ => (),
+        }
+        result
+    }
+}
+
+/// Serves pages previously stored by a `RecordingTransport`, without touching the network
+#[derive(Debug)]
+pub struct ReplayTransport {
+    dir: PathBuf,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: PathBuf) -> Self {
+        ReplayTransport { dir }
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn get(&self, url: &str) -> Result<String> {
+        let path = cassette_path(&self.dir, url);
+        let error = match tokio::fs::read_to_string(&path).await {
+            Ok(body) => return Ok(body),
+            Err(error) => error,
+        };
+        if let Ok(status) = tokio::fs::read_to_string(status_path(&self.dir, url)).await {
+            if let Ok(status) = status.trim().parse() {
+                return Err(Error::status(url, status));
+            }
+        }
+        Err(Error::io(&path, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::transport::MemoryTransport;
+
+    #[test]
+    fn test_cassette_path() {
+        assert_eq!(
+            cassette_path(
+                Path::new("pages"),
+                "http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-02/Dinner"
+            ),
+            Path::new(
+                "pages/http_3A_2F_2Fmenu.dining.ucla.edu_2FMenus_2FDeNeve_2F2021-10-02_2FDinner.html"
+            ),
+        );
+        // Urls differing only in punctuation get files of their own
+        assert_ne!(
+            cassette_path(Path::new("pages"), "http://localhost/Recipes/1/2"),
+            cassette_path(Path::new("pages"), "http://localhost/Recipes_1_2"),
+        );
+        assert_ne!(
+            cassette_path(Path::new("pages"), "http://localhost/Menus?date=1"),
+            cassette_path(Path::new("pages"), "http://localhost/Menus_date_1"),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "http://menu.dining.ucla.edu/Recipes/123056/1";
+
+        let mut memory = MemoryTransport::new();
+        memory.insert(url.into(), "<p>Fusilli</p>".into());
+        let recorder = RecordingTransport::new(memory, dir.path().into());
+        assert_eq!(recorder.get(url).await.unwrap(), "<p>Fusilli</p>");
+
+        let replay = ReplayTransport::new(dir.path().into());
+        assert_eq!(replay.get(url).await.unwrap(), "<p>Fusilli</p>");
+        assert!(replay
+            .get("http://menu.dining.ucla.edu/Recipes/138012/1")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "http://menu.dining.ucla.edu/Recipes/138012/1";
+
+        let mut memory = MemoryTransport::new();
+        memory.insert_status(url.into(), 503);
+        let recorder = RecordingTransport::new(memory, dir.path().into());
+        assert!(recorder.get(url).await.is_err());
+
+        let replay = ReplayTransport::new(dir.path().into());
+        let error = replay.get(url).await.unwrap_err();
+
+        assert!(matches!(error, Error::Status { status: 503, .. }));
+    }
+}
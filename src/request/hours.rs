@@ -0,0 +1,38 @@
+use crate::request::Downloadable;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use url::Url;
+
+/// Request for the page listing the hours of every restaurant and meal on a date
+#[derive(Debug, PartialEq)]
+pub struct HoursRequest {
+    pub base: Url,
+    pub date: NaiveDate,
+}
+
+impl HoursRequest {
+    pub fn new(base: Url, date: NaiveDate) -> Self {
+        HoursRequest { base, date }
+    }
+}
+
+#[async_trait]
+impl Downloadable for HoursRequest {
+    fn url(&self) -> String {
+        format!("{}Hours/{}", self.base, self.date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::default_base_url;
+
+    #[test]
+    fn test_hours_request_url() {
+        assert_eq!(
+            HoursRequest::new(default_base_url(), NaiveDate::from_ymd(2021, 10, 2)).url(),
+            "http://menu.dining.ucla.edu/Hours/2021-10-02"
+        );
+    }
+}
@@ -1,41 +1,52 @@
 use crate::request::Downloadable;
 use async_trait::async_trait;
+use url::Url;
 
 #[derive(Debug, PartialEq)]
 pub struct ItemRequest {
+    pub base: Url,
     pub id: String,
 }
 
 impl ItemRequest {
-    fn new(id: String) -> Self {
-        ItemRequest { id: id }
+    pub fn new(base: Url, id: String) -> Self {
+        ItemRequest { base, id }
     }
 }
 
 #[async_trait]
 impl Downloadable for ItemRequest {
     fn url(&self) -> String {
-        format!("http://menu.dining.ucla.edu/Recipes/{}/1", self.id)
+        format!("{}Recipes/{}/1", self.base, self.id)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::request::{default_base_url, parse_base_url};
 
     #[test]
     fn test_item_request_url() {
         assert_eq!(
-            ItemRequest::new("977026".to_string()).url(),
+            ItemRequest::new(default_base_url(), "977026".to_string()).url(),
             "http://menu.dining.ucla.edu/Recipes/977026/1"
         );
         assert_eq!(
-            ItemRequest::new("977085".to_string()).url(),
+            ItemRequest::new(default_base_url(), "977085".to_string()).url(),
             "http://menu.dining.ucla.edu/Recipes/977085/1"
         );
         assert_eq!(
-            ItemRequest::new("141301".to_string()).url(),
+            ItemRequest::new(default_base_url(), "141301".to_string()).url(),
             "http://menu.dining.ucla.edu/Recipes/141301/1"
         );
+        assert_eq!(
+            ItemRequest::new(
+                parse_base_url("http://localhost:8080/mirror").unwrap(),
+                "141301".to_string()
+            )
+            .url(),
+            "http://localhost:8080/mirror/Recipes/141301/1"
+        );
     }
 }
@@ -1,22 +1,26 @@
 use crate::model::{MealEnum, RestaurantEnum};
 use crate::request::Downloadable;
+use crate::{Error, Result};
 use async_trait::async_trait;
+use chrono::NaiveDate;
 use itertools::Itertools;
-use strum::IntoEnumIterator;
+use url::Url;
 
 #[derive(Debug, PartialEq)]
 pub struct MenuRequest {
-    pub date: String,
+    pub base: Url,
+    pub date: NaiveDate,
     pub restaurant: RestaurantEnum,
     pub meal: MealEnum,
 }
 
 impl MenuRequest {
-    fn new(date: String, restaurant: RestaurantEnum, meal: MealEnum) -> Self {
+    pub fn new(base: Url, date: NaiveDate, restaurant: RestaurantEnum, meal: MealEnum) -> Self {
         MenuRequest {
-            date: date,
-            restaurant: restaurant,
-            meal: meal,
+            base,
+            date,
+            restaurant,
+            meal,
         }
     }
 }
@@ -25,7 +29,8 @@ impl MenuRequest {
 impl Downloadable for MenuRequest {
     fn url(&self) -> String {
         format!(
-            "http://menu.dining.ucla.edu/Menus/{}/{}/{}",
+            "{}Menus/{}/{}/{}",
+            self.base,
             self.restaurant.url_name(),
             self.date,
             self.meal.url_name()
@@ -33,66 +38,130 @@ impl Downloadable for MenuRequest {
     }
 }
 
-/// Get all menu requests for a list of specific dates
-pub fn menu_requests_for_dates(dates: Vec<String>) -> Vec<MenuRequest> {
-    for date in &dates {
-        verify_date(date);
-    }
-
-    RestaurantEnum::iter()
-        .cartesian_product(dates)
-        .cartesian_product(MealEnum::iter())
-        .map(|((res, date), meal)| MenuRequest::new(date, res, meal))
-        .collect()
+/// Request for a restaurant's page on a specific date, whose tabs list the meals served that day
+#[derive(Debug, PartialEq)]
+pub struct MealsRequest {
+    pub base: Url,
+    pub date: NaiveDate,
+    pub restaurant: RestaurantEnum,
 }
 
-/// Verify that the date string is in correct YYYY-MM-DD format
-fn verify_date(date: &String) {
-    fn report_invalid_date(date: &String) {
-        panic!("Given date must be in YYYY-MM-DD format, got {}", date);
+impl MealsRequest {
+    pub fn new(base: Url, date: NaiveDate, restaurant: RestaurantEnum) -> Self {
+        MealsRequest {
+            base,
+            date,
+            restaurant,
+        }
     }
+}
 
-    fn is_numeric(s: &str) -> bool {
-        for c in s.to_string().chars() {
-            if !c.is_numeric() {
-                return false;
-            }
-        }
-        true
+#[async_trait]
+impl Downloadable for MealsRequest {
+    fn url(&self) -> String {
+        format!(
+            "{}Menus/{}/{}",
+            self.base,
+            self.restaurant.url_name(),
+            self.date,
+        )
     }
+}
 
-    let tokens = date.split("-").collect::<Vec<&str>>();
+/// Restaurants and meals to download menus for, by display name or URL slug as given by hand.
+/// An empty list doesn't filter anything out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MenuFilter {
+    pub restaurants: Vec<String>,
+    pub meals: Vec<String>,
+}
 
-    // Must have 3 tokens, a token for each date component
-    if tokens.len() != 3 {
-        report_invalid_date(date);
+impl MenuFilter {
+    pub fn includes_restaurant(&self, restaurant: &RestaurantEnum) -> bool {
+        self.restaurants.is_empty()
+            || self
+                .restaurants
+                .iter()
+                .any(|value| names(value, &restaurant.name(), &restaurant.url_name()))
     }
 
-    // Verify the year component
-    if tokens[0].len() != 4 || !is_numeric(tokens[0]) {
-        report_invalid_date(date);
+    pub fn includes_meal(&self, meal: &MealEnum) -> bool {
+        self.meals.is_empty()
+            || self
+                .meals
+                .iter()
+                .any(|value| names(value, &meal.name(), &meal.url_name()))
     }
 
-    // Verify the month component
-    if tokens[1].len() != 2 || !is_numeric(tokens[1]) {
-        report_invalid_date(date);
+    /// Fails on the first restaurant asked for that is none of the given ones
+    pub fn check_restaurants(&self, restaurants: &[RestaurantEnum]) -> Result<()> {
+        let options = restaurants.iter().map(|r| (r.name(), r.url_name()));
+        check(&self.restaurants, "restaurant", options.collect())
     }
 
-    // Verify the day component
-    if tokens[2].len() != 2 || !is_numeric(tokens[2]) {
-        report_invalid_date(date);
+    /// Fails on the first meal asked for that is none of the given ones
+    pub fn check_meals(&self, meals: &[MealEnum]) -> Result<()> {
+        let options = meals.iter().map(|m| (m.name(), m.url_name()));
+        check(&self.meals, "meal", options.collect())
+    }
+}
+
+/// Whether a value given by hand names something by display name or URL slug, ignoring case
+fn names(value: &str, name: &str, url_name: &str) -> bool {
+    value.eq_ignore_ascii_case(name) || value.eq_ignore_ascii_case(url_name)
+}
+
+fn check(values: &[String], kind: &str, options: Vec<(String, String)>) -> Result<()> {
+    let unmatched = values.iter().find(|value| {
+        !options
+            .iter()
+            .any(|(name, url_name)| names(value, name, url_name))
+    });
+    match unmatched {
+        Some(value) => Err(Error::UnmatchedFilter {
+            kind: kind.into(),
+            value: value.clone(),
+            options: options.into_iter().map(|(name, _)| name).collect(),
+        }),
+        None => Ok(()),
     }
 }
 
+/// Get all menu requests for a list of specific dates at the given restaurants and meals,
+/// leaving out those the filter excludes
+pub fn menu_requests_for_dates(
+    base: &Url,
+    dates: Vec<NaiveDate>,
+    restaurants: &[RestaurantEnum],
+    meals: &[MealEnum],
+    filter: &MenuFilter,
+) -> Vec<MenuRequest> {
+    restaurants
+        .iter()
+        .filter(|res| filter.includes_restaurant(res))
+        .cloned()
+        .cartesian_product(dates)
+        .cartesian_product(
+            meals
+                .iter()
+                .filter(|meal| filter.includes_meal(meal))
+                .cloned(),
+        )
+        .map(|((res, date), meal)| MenuRequest::new(base.clone(), date, res, meal))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::request::default_base_url;
 
     #[test]
     fn test_menu_request_url() {
         assert_eq!(
             MenuRequest::new(
-                "2021-09-28".into(),
+                default_base_url(),
+                NaiveDate::from_ymd(2021, 9, 28),
                 RestaurantEnum::BruinPlate,
                 MealEnum::Breakfast
             )
@@ -101,7 +170,8 @@ mod tests {
         );
         assert_eq!(
             MenuRequest::new(
-                "2021-09-28".into(),
+                default_base_url(),
+                NaiveDate::from_ymd(2021, 9, 28),
                 RestaurantEnum::DeNeve,
                 MealEnum::Breakfast
             )
@@ -110,103 +180,255 @@ mod tests {
         );
         assert_eq!(
             MenuRequest::new(
-                "2021-09-26".into(),
+                default_base_url(),
+                NaiveDate::from_ymd(2021, 9, 26),
                 RestaurantEnum::Epicuria,
                 MealEnum::Lunch
             )
             .url(),
             "http://menu.dining.ucla.edu/Menus/Epicuria/2021-09-26/Lunch",
         );
+        assert_eq!(
+            MenuRequest::new(
+                default_base_url(),
+                NaiveDate::from_ymd(2021, 9, 26),
+                RestaurantEnum::from_url_name("SpiceKitchen", "Spice Kitchen"),
+                MealEnum::Dinner
+            )
+            .url(),
+            "http://menu.dining.ucla.edu/Menus/SpiceKitchen/2021-09-26/Dinner",
+        );
+    }
+
+    #[test]
+    fn test_meals_request_url() {
+        assert_eq!(
+            MealsRequest::new(
+                default_base_url(),
+                NaiveDate::from_ymd(2021, 10, 2),
+                RestaurantEnum::BruinPlate
+            )
+            .url(),
+            "http://menu.dining.ucla.edu/Menus/BruinPlate/2021-10-02",
+        );
+    }
+
+    #[test]
+    fn test_menu_requests_for_brunch() {
+        assert_eq!(
+            menu_requests_for_dates(
+                &default_base_url(),
+                vec![NaiveDate::from_ymd(2021, 10, 2)],
+                &[RestaurantEnum::DeNeve],
+                &[MealEnum::Brunch, MealEnum::Dinner, MealEnum::LateNight],
+                &MenuFilter::default()
+            ),
+            vec![
+                MenuRequest::new(
+                    default_base_url(),
+                    NaiveDate::from_ymd(2021, 10, 2),
+                    RestaurantEnum::DeNeve,
+                    MealEnum::Brunch
+                ),
+                MenuRequest::new(
+                    default_base_url(),
+                    NaiveDate::from_ymd(2021, 10, 2),
+                    RestaurantEnum::DeNeve,
+                    MealEnum::Dinner
+                ),
+                MenuRequest::new(
+                    default_base_url(),
+                    NaiveDate::from_ymd(2021, 10, 2),
+                    RestaurantEnum::DeNeve,
+                    MealEnum::LateNight
+                ),
+            ]
+        );
     }
 
     #[test]
     fn test_menu_requests_for_dates() {
         assert_eq!(
-            menu_requests_for_dates(vec!["2020-08-18".into(), "2020-08-19".into()]),
+            menu_requests_for_dates(
+                &default_base_url(),
+                vec![
+                    NaiveDate::from_ymd(2020, 8, 18),
+                    NaiveDate::from_ymd(2020, 8, 19)
+                ],
+                &[
+                    RestaurantEnum::BruinPlate,
+                    RestaurantEnum::DeNeve,
+                    RestaurantEnum::Epicuria
+                ],
+                &MealEnum::defaults(),
+                &MenuFilter::default()
+            ),
             vec![
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::BruinPlate,
                     MealEnum::Breakfast
                 ),
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::BruinPlate,
                     MealEnum::Lunch
                 ),
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::BruinPlate,
                     MealEnum::Dinner
                 ),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::BruinPlate,
                     MealEnum::Breakfast
                 ),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::BruinPlate,
                     MealEnum::Lunch
                 ),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::BruinPlate,
                     MealEnum::Dinner
                 ),
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::DeNeve,
                     MealEnum::Breakfast
                 ),
-                MenuRequest::new("2020-08-18".into(), RestaurantEnum::DeNeve, MealEnum::Lunch),
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
+                    RestaurantEnum::DeNeve,
+                    MealEnum::Lunch
+                ),
+                MenuRequest::new(
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::DeNeve,
                     MealEnum::Dinner
                 ),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::DeNeve,
                     MealEnum::Breakfast
                 ),
-                MenuRequest::new("2020-08-19".into(), RestaurantEnum::DeNeve, MealEnum::Lunch),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
+                    RestaurantEnum::DeNeve,
+                    MealEnum::Lunch
+                ),
+                MenuRequest::new(
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::DeNeve,
                     MealEnum::Dinner
                 ),
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::Epicuria,
                     MealEnum::Breakfast
                 ),
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::Epicuria,
                     MealEnum::Lunch
                 ),
                 MenuRequest::new(
-                    "2020-08-18".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 18),
                     RestaurantEnum::Epicuria,
                     MealEnum::Dinner
                 ),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::Epicuria,
                     MealEnum::Breakfast
                 ),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::Epicuria,
                     MealEnum::Lunch
                 ),
                 MenuRequest::new(
-                    "2020-08-19".into(),
+                    default_base_url(),
+                    NaiveDate::from_ymd(2020, 8, 19),
                     RestaurantEnum::Epicuria,
                     MealEnum::Dinner
                 ),
             ]
         );
     }
+
+    #[test]
+    fn test_menu_requests_filtered() {
+        let filter = MenuFilter {
+            restaurants: vec!["deneve".into()],
+            meals: vec!["DINNER".into()],
+        };
+        assert_eq!(
+            menu_requests_for_dates(
+                &default_base_url(),
+                vec![NaiveDate::from_ymd(2021, 10, 2)],
+                &[RestaurantEnum::BruinPlate, RestaurantEnum::DeNeve],
+                &MealEnum::defaults(),
+                &filter
+            ),
+            vec![MenuRequest::new(
+                default_base_url(),
+                NaiveDate::from_ymd(2021, 10, 2),
+                RestaurantEnum::DeNeve,
+                MealEnum::Dinner
+            )]
+        );
+    }
+
+    #[test]
+    fn test_check_filter() {
+        let restaurants = vec![
+            RestaurantEnum::DeNeve,
+            RestaurantEnum::from_url_name("SpiceKitchen", "Spice Kitchen"),
+        ];
+        let filter = MenuFilter {
+            restaurants: vec!["de neve".into(), "spicekitchen".into()],
+            meals: vec!["late night".into()],
+        };
+        assert!(filter.check_restaurants(&restaurants).is_ok());
+        assert!(filter.includes_restaurant(&restaurants[1]));
+        assert!(filter.check_meals(&[MealEnum::LateNight]).is_ok());
+
+        let error = filter.check_meals(&MealEnum::defaults()).unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::UnmatchedFilter { kind, value, .. } if kind == "meal" && value == "late night"
+        ));
+
+        let filter = MenuFilter {
+            restaurants: vec!["Covel".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            filter
+                .check_restaurants(&restaurants)
+                .unwrap_err()
+                .to_string(),
+            "no restaurant matches \"Covel\", expected one of: De Neve, Spice Kitchen"
+        );
+    }
 }
@@ -1,33 +1,664 @@
+pub mod cache;
+pub mod cassette;
+pub mod hours;
 pub mod item;
 pub mod menu;
+pub mod politeness;
+pub mod restaurants;
+pub mod retry;
+pub mod transport;
 
-use crate::model::DateMenu;
-use crate::parse::parse_menu;
+use crate::model::catalog::ItemCatalog;
+use crate::model::{
+    DateMenu, Item, ItemDetails, MealEnum, RestaurantEnum, RestaurantMenu, Section,
+};
+use crate::parse::{parse_hours, parse_item, parse_meals, parse_menu, parse_restaurants, Parsed};
+use crate::{Error, Result};
 use async_trait::async_trait;
+use chrono::NaiveDate;
+use futures::stream::{self, StreamExt};
+use hours::HoursRequest;
+use item::ItemRequest;
+use menu::{MealsRequest, MenuFilter};
+use restaurants::RestaurantsRequest;
+use retry::RetryPolicy;
+use std::collections::{HashMap, HashSet};
+use transport::Transport;
+use url::Url;
+
+/// The dining site scraped unless another base url is configured
+pub const DEFAULT_BASE_URL: &str = "http://menu.dining.ucla.edu/";
+
+/// Parse the base url of the dining site or a mirror of it, which all requests are made against.
+/// A trailing slash is added when missing, so that pages are resolved below its path.
+pub fn parse_base_url(base_url: &str) -> std::result::Result<Url, url::ParseError> {
+    if base_url.ends_with('/') {
+        Url::parse(base_url)
+    } else {
+        Url::parse(&format!("{}/", base_url))
+    }
+}
+
+pub fn default_base_url() -> Url {
+    parse_base_url(DEFAULT_BASE_URL).unwrap()
+}
 
 #[async_trait]
 pub trait Downloadable {
     fn url(&self) -> String;
 
-    async fn download(&self) -> Result<String, Box<dyn std::error::Error>> {
-        let body = reqwest::get(self.url().as_str()).await?.text().await?;
-        Ok(body)
+    async fn download(&self, transport: &dyn Transport) -> Result<String> {
+        transport.get(self.url().as_str()).await
+    }
+
+    /// Download the page, retrying transient failures as allowed by the policy
+    async fn download_with_retry(
+        &self,
+        transport: &dyn Transport,
+        policy: &RetryPolicy,
+    ) -> Result<String> {
+        let mut attempt = 1;
+        loop {
+            match self.download(transport).await {
+                Ok(body) => return Ok(body),
+                Err(error) if attempt < policy.max_attempts && error.is_retryable() => {
+                    tokio::time::sleep(policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(error) => return Err(error.after_attempts(attempt)),
+            }
+        }
+    }
+}
+
+/// Keep the parsed value, collecting its diagnostics as errors of the page at the url
+fn take_parsed<T>(parsed: Parsed<T>, url: String, errors: &mut Vec<Error>) -> T {
+    errors.extend(parsed.diagnostics.into_iter().map(|error| Error::Parse {
+        url: url.clone(),
+        error,
+    }));
+    parsed.value
+}
+
+/// Settings shared by all downloads of a scraping run
+#[derive(Debug, Clone)]
+pub struct DownloadConfig {
+    /// Maximum number of pages downloaded at the same time, where 0 is treated as 1
+    pub max_in_flight: usize,
+    pub retry: RetryPolicy,
+    /// Base url of the dining site, or of a mirror serving the same pages
+    pub base_url: Url,
+    /// Restaurants and meals whose menus are downloaded
+    pub filter: MenuFilter,
+}
+
+impl DownloadConfig {
+    /// Number of pages to download at the same time, which is never 0 so downloads cannot stall
+    fn in_flight(&self) -> usize {
+        self.max_in_flight.max(1)
+    }
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        DownloadConfig {
+            max_in_flight: 8,
+            retry: RetryPolicy::default(),
+            base_url: default_base_url(),
+            filter: MenuFilter::default(),
+        }
     }
 }
 
-pub async fn download_menus(date: String) -> Result<DateMenu, Box<dyn std::error::Error>> {
-    let requests = menu::menu_requests_for_dates(vec![date.clone()]);
+/// Discover all restaurants listed on the dining site landing page, along with the links that
+/// could not be parsed
+pub async fn discover_restaurants(
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) -> Result<(Vec<RestaurantEnum>, Vec<Error>)> {
+    let request = RestaurantsRequest::new(config.base_url.clone());
+    let body = request
+        .download_with_retry(transport, &config.retry)
+        .await?;
+    let mut errors = Vec::new();
+    let parsed = parse_restaurants::parse(body.as_str(), &request);
+    Ok((take_parsed(parsed, request.url(), &mut errors), errors))
+}
+
+/// Discover all meals a restaurant serves on a date from the meal tabs on its page, along with
+/// the links that could not be parsed
+pub async fn discover_meals(
+    date: NaiveDate,
+    restaurant: RestaurantEnum,
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) -> Result<(Vec<MealEnum>, Vec<Error>)> {
+    let request = MealsRequest::new(config.base_url.clone(), date, restaurant);
+    let body = request
+        .download_with_retry(transport, &config.retry)
+        .await?;
+    let mut errors = Vec::new();
+    let parsed = parse_meals::parse(body.as_str(), &request);
+    Ok((take_parsed(parsed, request.url(), &mut errors), errors))
+}
+
+/// Download the menus of all meals at the given restaurants on a date.
+///
+/// Meals whose menus fail to download are marked as failed in the returned menu and reported
+/// alongside it, as are the parts of menus that fail to parse. Also returns every meal the
+/// restaurants' pages list for the date, including those the filter leaves out.
+pub async fn download_menus(
+    date: NaiveDate,
+    restaurants: &[RestaurantEnum],
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) -> (DateMenu, Vec<MealEnum>, Vec<Error>) {
     let mut date_menu = DateMenu {
-        date: date.clone(),
+        date,
         restaurants: Vec::new(),
+        calendar: None,
     };
 
-    for request in requests {
-        if let Ok(body) = request.download().await {
-            let menu = parse_menu::parse(body.as_str(), &request);
-            date_menu.add_restaurant(menu);
+    let restaurants = restaurants
+        .iter()
+        .filter(|r| config.filter.includes_restaurant(r))
+        .cloned()
+        .collect::<Vec<_>>();
+    let meals = stream::iter(&restaurants)
+        .map(|restaurant| discover_meals(date, restaurant.clone(), transport, config))
+        .buffered(config.in_flight())
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut errors = Vec::new();
+    let mut requests = Vec::new();
+    let mut served: Vec<MealEnum> = Vec::new();
+    for (restaurant, meals) in restaurants.iter().zip(meals) {
+        let meals = match meals {
+            Ok((meals, link_errors)) if !meals.is_empty() => {
+                errors.extend(link_errors);
+                // Fallback meals are left out, as they would match any meal asked for
+                for meal in &meals {
+                    if !served.contains(meal) {
+                        served.push(meal.clone());
+                    }
+                }
+                meals
+            }
+            Err(error) => {
+                // Still try the usual meals, as the menu pages may load when the tabs didn't
+                errors.push(error);
+                fallback_meals(config)
+            }
+            Ok((_, link_errors)) => {
+                errors.extend(link_errors);
+                fallback_meals(config)
+            }
+        };
+        requests.extend(menu::menu_requests_for_dates(
+            &config.base_url,
+            vec![date],
+            std::slice::from_ref(restaurant),
+            &meals,
+            &config.filter,
+        ));
+    }
+
+    // Buffered streams yield results in request order, keeping the menu layout deterministic
+    let bodies = stream::iter(&requests)
+        .map(|request| request.download_with_retry(transport, &config.retry))
+        .buffered(config.in_flight())
+        .collect::<Vec<_>>()
+        .await;
+
+    for (request, body) in requests.iter().zip(bodies) {
+        match body {
+            Ok(body) => {
+                let parsed = parse_menu::parse(body.as_str(), request);
+                date_menu.add_restaurant(take_parsed(parsed, request.url(), &mut errors));
+            }
+            Err(error) => {
+                date_menu.add_restaurant(RestaurantMenu::failed(
+                    request.date,
+                    request.restaurant.clone(),
+                    request.meal.clone(),
+                ));
+                errors.push(error);
+            }
+        }
+    }
+
+    // Menus are still useful without hours, so a missing hours page is only reported
+    let hours_request = HoursRequest::new(config.base_url.clone(), date);
+    match hours_request
+        .download_with_retry(transport, &config.retry)
+        .await
+    {
+        Ok(body) => {
+            let parsed = parse_hours::parse(body.as_str(), &hours_request);
+            date_menu.set_hours(&take_parsed(parsed, hours_request.url(), &mut errors));
         }
+        Err(error) => errors.push(error),
+    }
+
+    (date_menu, served, errors)
+}
+
+/// Meals to request when a restaurant's meal tabs can't be discovered
+fn fallback_meals(config: &DownloadConfig) -> Vec<MealEnum> {
+    // Meals asked for by name are worth trying even when they aren't served by default
+    if config.filter.meals.is_empty() {
+        MealEnum::defaults()
+    } else {
+        config
+            .filter
+            .meals
+            .iter()
+            .map(|meal| MealEnum::from_name(meal))
+            .collect()
+    }
+}
+
+/// An item whose details could not be downloaded or parsed
+#[derive(Debug)]
+pub struct DetailsError {
+    pub item_id: String,
+    pub error: Error,
+}
+
+impl std::fmt::Display for DetailsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "item {}: {}", self.item_id, self.error)
+    }
+}
+
+/// Download details of all items in every restaurant and meal of a day's menu.
+///
+/// Each recipe is downloaded at most once: details already in the catalog are reused, and
+/// newly downloaded ones are added to it. Items whose details fail to download are left without
+/// details and reported in the result.
+pub async fn inflate_item_details(
+    menu: &mut DateMenu,
+    catalog: &mut ItemCatalog,
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) -> Vec<DetailsError> {
+    let mut sections = menu
+        .restaurants
+        .iter_mut()
+        .flat_map(|r| r.meals.iter_mut())
+        .flat_map(|m| m.sections.iter_mut())
+        .collect::<Vec<&mut Section>>();
+    inflate_sections(&mut sections, catalog, transport, config).await
+}
+
+/// Download details of all items in a single restaurant menu
+pub async fn inflate_restaurant_item_details(
+    menu: &mut RestaurantMenu,
+    catalog: &mut ItemCatalog,
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) -> Vec<DetailsError> {
+    let mut sections = menu.sections.iter_mut().collect::<Vec<&mut Section>>();
+    inflate_sections(&mut sections, catalog, transport, config).await
+}
+
+async fn inflate_sections(
+    sections: &mut [&mut Section],
+    catalog: &mut ItemCatalog,
+    transport: &dyn Transport,
+    config: &DownloadConfig,
+) -> Vec<DetailsError> {
+    let mut requests = Vec::new();
+    let mut seen = HashSet::new();
+    for section in sections.iter() {
+        collect_item_requests(
+            &section.items,
+            catalog,
+            &config.base_url,
+            &mut seen,
+            &mut requests,
+        );
     }
 
-    Ok(date_menu)
+    let bodies = stream::iter(&requests)
+        .map(|request| request.download_with_retry(transport, &config.retry))
+        .buffered(config.in_flight())
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut errors = Vec::new();
+    // Details that only partly parsed are used for this menu but kept out of the catalog, so
+    // later runs download them again
+    let mut partial = HashMap::new();
+    for (request, body) in requests.iter().zip(bodies) {
+        match body {
+            Ok(body) => {
+                let mut page_errors = Vec::new();
+                let parsed = parse_item::parse(body.as_str());
+                let details = take_parsed(parsed, request.url(), &mut page_errors);
+                if page_errors.is_empty() {
+                    catalog.insert(request.id.clone(), details);
+                } else {
+                    partial.insert(request.id.clone(), details);
+                }
+                errors.extend(page_errors.into_iter().map(|error| DetailsError {
+                    item_id: request.id.clone(),
+                    error,
+                }));
+            }
+            Err(error) => errors.push(DetailsError {
+                item_id: request.id.clone(),
+                error,
+            }),
+        }
+    }
+
+    for section in sections.iter_mut() {
+        fill_item_details(&mut section.items, catalog, &partial);
+    }
+    errors
+}
+
+/// Collects one request per recipe that is neither in the catalog nor already requested
+fn collect_item_requests(
+    items: &[Item],
+    catalog: &ItemCatalog,
+    base: &Url,
+    seen: &mut HashSet<String>,
+    requests: &mut Vec<ItemRequest>,
+) {
+    for item in items {
+        if !catalog.contains(&item.id) && seen.insert(item.id.clone()) {
+            requests.push(item.details_request(base));
+        }
+        collect_item_requests(&item.accompaniments, catalog, base, seen, requests);
+    }
+}
+
+fn fill_item_details(
+    items: &mut [Item],
+    catalog: &ItemCatalog,
+    partial: &HashMap<String, ItemDetails>,
+) {
+    for item in items {
+        if let Some(details) = catalog.get(&item.id).or_else(|| partial.get(&item.id)) {
+            item.set_details(details.clone());
+        }
+        fill_item_details(&mut item.accompaniments, catalog, partial);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Availability, Hours};
+    use chrono::NaiveTime;
+    use std::time::Duration;
+    use transport::MemoryTransport;
+
+    fn item(id: &str, accompaniments: Vec<Item>) -> Item {
+        Item {
+            id: id.into(),
+            accompaniments,
+            ..Default::default()
+        }
+    }
+
+    fn details(description: &str) -> ItemDetails {
+        ItemDetails {
+            description: Some(description.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_item_requests_skip_known_and_duplicate_recipes() {
+        let items = vec![
+            item("123056", vec![item("138012", vec![])]),
+            item("141301", vec![]),
+            item("977026", vec![item("138012", vec![])]),
+        ];
+        let mut catalog = ItemCatalog::new();
+        catalog.insert("141301".into(), details("Vegetables"));
+
+        let mut requests = Vec::new();
+        collect_item_requests(
+            &items,
+            &catalog,
+            &default_base_url(),
+            &mut HashSet::new(),
+            &mut requests,
+        );
+        assert_eq!(
+            requests,
+            vec![
+                ItemRequest::new(default_base_url(), "123056".into()),
+                ItemRequest::new(default_base_url(), "138012".into()),
+                ItemRequest::new(default_base_url(), "977026".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_item_details_from_catalog() {
+        let mut items = vec![
+            item("123056", vec![item("138012", vec![])]),
+            item("141301", vec![item("138012", vec![])]),
+        ];
+        let mut catalog = ItemCatalog::new();
+        catalog.insert("123056".into(), details("Fusilli"));
+        catalog.insert("138012".into(), details("Bread"));
+
+        fill_item_details(&mut items, &catalog, &HashMap::new());
+        assert_eq!(items[0].details, Some(details("Fusilli")));
+        assert_eq!(items[0].accompaniments[0].details, Some(details("Bread")));
+        assert_eq!(items[1].details, None);
+        assert_eq!(items[1].accompaniments[0].details, Some(details("Bread")));
+    }
+
+    fn no_backoff(max_attempts: u32) -> DownloadConfig {
+        DownloadConfig {
+            max_in_flight: 2,
+            retry: RetryPolicy {
+                max_attempts,
+                initial_backoff: Duration::from_millis(0),
+                max_backoff: Duration::from_millis(0),
+                jitter: false,
+            },
+            base_url: default_base_url(),
+            filter: MenuFilter::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_menus_reports_failed_pages() {
+        let mut transport = MemoryTransport::new();
+        transport.insert(
+            "http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-02".into(),
+            r#"<a href="/Menus/DeNeve/2021-10-02/Dinner">Dinner</a>"#.into(),
+        );
+        transport.insert(
+            "http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-02/Dinner".into(),
+            r#"
+<ul class="sect-list">
+  <li class="sect-item">
+    Grill
+    <ul class="item-list">
+      <li class="menu-item">
+        <span class="tooltip-target-wrapper">
+          <a class="recipelink" href="http://menu.dining.ucla.edu/Recipes/123056/6">Fusilli</a>
+        </span>
+      </li>
+    </ul>
+  </li>
+</ul>
+            "#
+            .into(),
+        );
+
+        transport.insert(
+            "http://menu.dining.ucla.edu/Hours/2021-10-02".into(),
+            r#"
+<table class="hours-table">
+  <tr><th>Location</th><th>Dinner</th></tr>
+  <tr>
+    <td class="hours-head"><a href="/Menus/DeNeve">De Neve</a></td>
+    <td class="hours-open"><span class="hours-range">5:00 p.m. - 9:00 p.m.</span></td>
+  </tr>
+</table>
+            "#
+            .into(),
+        );
+
+        let restaurants = vec![RestaurantEnum::DeNeve, RestaurantEnum::BruinPlate];
+        let (menu, served, errors) = download_menus(
+            NaiveDate::from_ymd(2021, 10, 2),
+            &restaurants,
+            &transport,
+            &no_backoff(3),
+        )
+        .await;
+
+        assert_eq!(menu.restaurants.len(), 2);
+        assert_eq!(served, vec![MealEnum::Dinner]);
+        assert_eq!(menu.restaurants[0].name, RestaurantEnum::DeNeve);
+        assert_eq!(menu.restaurants[0].meals[0].name, MealEnum::Dinner);
+        assert_eq!(
+            menu.restaurants[0].meals[0].availability,
+            Availability::Open
+        );
+        assert_eq!(
+            menu.restaurants[0].meals[0].hours,
+            Some(Hours {
+                open: NaiveTime::from_hms(17, 0, 0),
+                close: NaiveTime::from_hms(21, 0, 0),
+            })
+        );
+        assert!(menu.restaurants[1]
+            .meals
+            .iter()
+            .all(|m| m.availability == Availability::FetchFailed));
+        assert_eq!(
+            menu.restaurants[0].meals[0].sections[0].items[0].id,
+            "123056"
+        );
+
+        // Bruin Plate's meal tabs are missing, so it falls back to the default meals, none of
+        // which could be downloaded either
+        let failed = errors.iter().map(|e| e.url().unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            failed,
+            vec![
+                "http://menu.dining.ucla.edu/Menus/BruinPlate/2021-10-02",
+                "http://menu.dining.ucla.edu/Menus/BruinPlate/2021-10-02/Breakfast",
+                "http://menu.dining.ucla.edu/Menus/BruinPlate/2021-10-02/Lunch",
+                "http://menu.dining.ucla.edu/Menus/BruinPlate/2021-10-02/Dinner",
+            ]
+        );
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e, Error::Status { attempts: 1, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_download_menus_skips_unserved_meals() {
+        let mut transport = MemoryTransport::new();
+        transport.insert(
+            "http://menu.dining.ucla.edu/Menus/DeNeve/2021-10-02".into(),
+            r#"<a href="/Menus/DeNeve/2021-10-02/Dinner">Dinner</a>"#.into(),
+        );
+        let mut config = no_backoff(1);
+        config.max_in_flight = 0;
+        config.filter.meals = vec!["Lunch".into()];
+
+        let (menu, served, errors) = download_menus(
+            NaiveDate::from_ymd(2021, 10, 2),
+            &[RestaurantEnum::DeNeve],
+            &transport,
+            &config,
+        )
+        .await;
+
+        // Only the meals page and hours are requested, as no meal is left to download. A meal
+        // not served on one date is no failure, only the missing hours page is.
+        assert!(menu.restaurants.is_empty());
+        assert_eq!(served, vec![MealEnum::Dinner]);
+        assert_eq!(transport.requested().len(), 2);
+        assert_eq!(
+            errors.iter().map(|e| e.url()).collect::<Vec<_>>(),
+            vec![Some("http://menu.dining.ucla.edu/Hours/2021-10-02")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inflate_retries_server_errors() {
+        let mut transport = MemoryTransport::new();
+        transport.insert(
+            "http://menu.dining.ucla.edu/Recipes/123056/1".into(),
+            r#"<div class="productinfo"><div class="description">Fusilli</div></div>"#.into(),
+        );
+        transport.insert_status("http://menu.dining.ucla.edu/Recipes/138012/1".into(), 503);
+
+        let mut menu = RestaurantMenu {
+            date: NaiveDate::from_ymd(2021, 10, 2),
+            restaurant: RestaurantEnum::DeNeve,
+            meal: MealEnum::Dinner,
+            availability: Availability::Open,
+            sections: vec![Section {
+                name: "Grill".into(),
+                items: vec![item("123056", vec![item("138012", vec![])])],
+            }],
+        };
+        let mut catalog = ItemCatalog::new();
+        let errors =
+            inflate_restaurant_item_details(&mut menu, &mut catalog, &transport, &no_backoff(3))
+                .await;
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].item_id, "138012");
+        assert!(matches!(
+            errors[0].error,
+            Error::Status {
+                attempts: 3,
+                status: 503,
+                ..
+            }
+        ));
+        assert!(catalog.contains("123056"));
+        assert!(menu.sections[0].items[0].details.is_some());
+        assert!(menu.sections[0].items[0].accompaniments[0]
+            .details
+            .is_none());
+        assert_eq!(transport.requested().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_inflate_keeps_partial_details_out_of_catalog() {
+        let mut transport = MemoryTransport::new();
+        transport.insert(
+            "http://menu.dining.ucla.edu/Recipes/123056/1".into(),
+            "<h2>Fusilli</h2>".into(),
+        );
+
+        let mut menu = RestaurantMenu {
+            date: NaiveDate::from_ymd(2021, 10, 2),
+            restaurant: RestaurantEnum::DeNeve,
+            meal: MealEnum::Dinner,
+            availability: Availability::Open,
+            sections: vec![Section {
+                name: "Grill".into(),
+                items: vec![item("123056", vec![])],
+            }],
+        };
+        let mut catalog = ItemCatalog::new();
+        let errors =
+            inflate_restaurant_item_details(&mut menu, &mut catalog, &transport, &no_backoff(1))
+                .await;
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].error, Error::Parse { .. }));
+        assert!(menu.sections[0].items[0].details.is_some());
+        assert!(!catalog.contains("123056"));
+    }
 }
@@ -0,0 +1,355 @@
+use super::transport::{Response, Transport};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+pub const DEFAULT_USER_AGENT: &str = concat!("ucla_dining_scraper/", env!("CARGO_PKG_VERSION"));
+
+/// Slowest supported rate, one request every 100 seconds
+pub const MIN_RATE: f64 = 0.01;
+/// Fastest supported rate in requests per second
+pub const MAX_RATE: f64 = 1000.0;
+
+/// Token bucket allowing bursts of `capacity` requests, refilled at `rate` requests per second
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Rates outside `MIN_RATE..=MAX_RATE`, or not a number at all, are clamped to that range
+    pub fn new(capacity: u32, rate: f64, now: Instant) -> Self {
+        TokenBucket {
+            capacity: capacity.max(1) as f64,
+            rate: if rate.is_nan() {
+                MIN_RATE
+            } else {
+                rate.clamp(MIN_RATE, MAX_RATE)
+            },
+            tokens: capacity.max(1) as f64,
+            updated: now,
+        }
+    }
+
+    /// Take a token, returning how long to wait before it may be used.
+    ///
+    /// Tokens are reserved even when the bucket is empty, so concurrent callers queue up instead of
+    /// all waking at the same time.
+    pub fn take(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity) - 1.0;
+        self.updated = now;
+        if self.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// Rules of a robots.txt file that apply to one user agent
+#[derive(Debug, Default, PartialEq)]
+pub struct RobotsTxt {
+    /// Allow (true) and disallow (false) path prefixes
+    rules: Vec<(bool, String)>,
+}
+
+impl RobotsTxt {
+    /// Parse the rules for the user agent, falling back to the rules for all agents (`*`)
+    pub fn parse(doc: &str, user_agent: &str) -> Self {
+        let products = product_names(user_agent);
+        let mut specific: Option<Vec<(bool, String)>> = None;
+        let mut any: Option<Vec<(bool, String)>> = None;
+
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        let mut rules: Vec<(bool, String)> = Vec::new();
+        let mut flush = |agents: &[String], rules: &mut Vec<(bool, String)>| {
+            for agent in agents {
+                if agent == "*" {
+                    any.get_or_insert_with(Vec::new)
+                        .extend(rules.iter().cloned());
+                } else if names_agent(agent, &products) {
+                    specific
+                        .get_or_insert_with(Vec::new)
+                        .extend(rules.iter().cloned());
+                }
+            }
+            rules.clear();
+        };
+
+        for line in doc.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            match field.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        flush(&agents, &mut rules);
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty disallow allows everything
+                    if !value.is_empty() {
+                        rules.push((field == "allow", value.into()));
+                    }
+                }
+                _ => (),
+            }
+        }
+        flush(&agents, &mut rules);
+
+        RobotsTxt {
+            rules: specific.or(any).unwrap_or_default(),
+        }
+    }
+
+    /// Whether the path may be fetched, decided by the longest matching rule
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let rule = self
+            .rules
+            .iter()
+            .filter(|(_, prefix)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(allow, prefix)| (prefix.len(), *allow));
+        !matches!(rule, Some((false, _)))
+    }
+}
+
+/// Product names in a user agent, like `ucla_dining_scraper` and `otherbot` in
+/// `ucla_dining_scraper/0.1.0 (compatible; OtherBot/1.0)`, in lowercase
+fn product_names(user_agent: &str) -> Vec<String> {
+    user_agent
+        .split(|c: char| c.is_whitespace() || "();,".contains(c))
+        .filter_map(|product| product.split('/').next())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_ascii_lowercase())
+        .collect()
+}
+
+/// Whether a robots.txt `User-agent` line, other than `*`, names one of the products
+fn names_agent(agent: &str, products: &[String]) -> bool {
+    let name = agent.split('/').next().unwrap_or_default().trim();
+    !name.is_empty()
+        && products
+            .iter()
+            .any(|product| product.eq_ignore_ascii_case(name))
+}
+
+/// Throttles requests to each host and optionally checks robots.txt before every fetch
+#[derive(Debug)]
+pub struct PoliteTransport<T> {
+    inner: T,
+    user_agent: String,
+    burst: u32,
+    rate: f64,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    robots: Option<tokio::sync::Mutex<HashMap<String, RobotsTxt>>>,
+}
+
+impl<T: Transport> PoliteTransport<T> {
+    /// Allow `rate` requests per second to every host, in bursts of up to `burst` requests.
+    /// The user agent should be the one the inner transport sends.
+    pub fn new(inner: T, user_agent: String, rate: f64, burst: u32) -> Self {
+        PoliteTransport {
+            inner,
+            user_agent,
+            burst,
+            rate,
+            buckets: Mutex::new(HashMap::new()),
+            robots: None,
+        }
+    }
+
+    /// Refuse to fetch pages that robots.txt disallows for our user agent
+    pub fn respect_robots_txt(mut self, respect: bool) -> Self {
+        self.robots = if respect {
+            Some(tokio::sync::Mutex::new(HashMap::new()))
+        } else {
+            None
+        };
+        self
+    }
+
+    async fn throttle(&self, url: &Url) {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let wait = self
+            .buckets
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_insert_with(|| TokenBucket::new(self.burst, self.rate, Instant::now()))
+            .take(Instant::now());
+        if wait > Duration::from_secs(0) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn is_allowed(&self, url: &Url) -> Result<bool> {
+        let robots = match &self.robots {
+            Some(robots) => robots,
+            None => return Ok(true),
+        };
+        let origin = url.origin().ascii_serialization();
+        // Holding the lock while fetching makes parallel downloads wait for a single fetch
+        let mut robots = robots.lock().await;
+        if !robots.contains_key(&origin) {
+            let robots_url = url
+                .join("/robots.txt")
+                .map_err(|e| Error::invalid_url(url.as_str(), e))?;
+            self.throttle(&robots_url).await;
+            let rules = match self.inner.get(robots_url.as_str()).await {
+                Ok(doc) => RobotsTxt::parse(&doc, &self.user_agent),
+                // A site without robots.txt allows everything
+                Err(Error::Status { status, .. }) if status < 500 => RobotsTxt::default(),
+                Err(error) => return Err(error),
+            };
+            robots.insert(origin.clone(), rules);
+        }
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        Ok(robots[&origin].is_allowed(&path))
+    }
+
+    /// Wait for our turn to fetch the url, if robots.txt allows it at all
+    async fn wait_turn(&self, url: &str) -> Result<()> {
+        let parsed = Url::parse(url).map_err(|e| Error::invalid_url(url, e))?;
+        if !self.is_allowed(&parsed).await? {
+            return Err(Error::Disallowed { url: url.into() });
+        }
+        self.throttle(&parsed).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for PoliteTransport<T> {
+    async fn get(&self, url: &str) -> Result<String> {
+        self.wait_turn(url).await?;
+        self.inner.get(url).await
+    }
+
+    async fn fetch(&self, url: &str, headers: HeaderMap) -> Result<Response> {
+        self.wait_turn(url).await?;
+        self.inner.fetch(url, headers).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::transport::MemoryTransport;
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, 4.0, start);
+        assert_eq!(bucket.take(start), Duration::from_secs(0));
+        assert_eq!(bucket.take(start), Duration::from_secs(0));
+        assert_eq!(bucket.take(start), Duration::from_millis(250));
+        assert_eq!(bucket.take(start), Duration::from_millis(500));
+
+        // Refilled, but never above capacity
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.take(later), Duration::from_secs(0));
+        assert_eq!(bucket.take(later), Duration::from_secs(0));
+        assert_eq!(bucket.take(later), Duration::from_millis(250));
+
+        // Unusable rates are clamped instead of disabling the limit or overflowing the wait
+        let mut bucket = TokenBucket::new(1, f64::NAN, start);
+        bucket.take(start);
+        assert_eq!(bucket.take(start), Duration::from_secs(100));
+        let mut bucket = TokenBucket::new(1, 1e-20, start);
+        bucket.take(start);
+        assert_eq!(bucket.take(start), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_robots_txt() {
+        let doc = "
+# Comments are ignored
+User-agent: *
+Disallow: /Menus/Today
+Disallow: /Recipes/
+Allow: /Recipes/Public
+
+User-agent: BadBot
+User-agent: OtherBot
+Disallow: /
+";
+        let robots = RobotsTxt::parse(doc, DEFAULT_USER_AGENT);
+        assert!(robots.is_allowed("/Menus/DeNeve/2021-10-02/Dinner"));
+        assert!(!robots.is_allowed("/Menus/Today"));
+        assert!(!robots.is_allowed("/Recipes/977026/1"));
+        assert!(robots.is_allowed("/Recipes/Public/1"));
+
+        let robots = RobotsTxt::parse(doc, "Mozilla/5.0 (compatible; OtherBot/1.0)");
+        assert!(!robots.is_allowed("/Menus/DeNeve"));
+
+        assert!(RobotsTxt::parse("User-agent: *\nDisallow:\n", "bot").is_allowed("/Menus"));
+
+        // Groups only apply to agents they name in full, not to any agent containing the value
+        for agent in ["", "u", "dining", "ucla_dining_scraper_v2"] {
+            let doc = format!("User-agent: {}\nDisallow: /\n", agent);
+            assert!(RobotsTxt::parse(&doc, DEFAULT_USER_AGENT).is_allowed("/Menus"));
+        }
+        let doc = "User-agent: UCLA_Dining_Scraper/0.1\nDisallow: /\n";
+        assert!(!RobotsTxt::parse(doc, DEFAULT_USER_AGENT).is_allowed("/Menus"));
+    }
+
+    #[tokio::test]
+    async fn test_robots_txt_gates_fetches() {
+        let mut memory = MemoryTransport::new();
+        memory.insert(
+            "http://menu.dining.ucla.edu/robots.txt".into(),
+            "User-agent: *\nDisallow: /Recipes/\n".into(),
+        );
+        memory.insert(
+            "http://menu.dining.ucla.edu/Menus".into(),
+            "<p>Menus</p>".into(),
+        );
+        memory.insert(
+            "http://menu.dining.ucla.edu/Recipes/977026/1".into(),
+            "<p>Soup</p>".into(),
+        );
+        let transport = PoliteTransport::new(memory, DEFAULT_USER_AGENT.into(), 100.0, 10)
+            .respect_robots_txt(true);
+
+        assert!(transport
+            .get("http://menu.dining.ucla.edu/Menus")
+            .await
+            .is_ok());
+        let error = transport
+            .get("http://menu.dining.ucla.edu/Recipes/977026/1")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Disallowed { .. }));
+
+        let error = transport.get("/Menus").await.unwrap_err();
+        assert!(matches!(error, Error::InvalidUrl { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_missing_robots_txt_allows_everything() {
+        let mut memory = MemoryTransport::new();
+        memory.insert("http://localhost:8080/Menus".into(), "<p>Menus</p>".into());
+        let transport = PoliteTransport::new(memory, DEFAULT_USER_AGENT.into(), 100.0, 10)
+            .respect_robots_txt(true);
+        assert!(transport.get("http://localhost:8080/Menus").await.is_ok());
+    }
+}
@@ -0,0 +1,36 @@
+use crate::request::Downloadable;
+use async_trait::async_trait;
+use url::Url;
+
+/// Request for the dining site landing page, which links to every dining hall
+#[derive(Debug, PartialEq)]
+pub struct RestaurantsRequest {
+    pub base: Url,
+}
+
+impl RestaurantsRequest {
+    pub fn new(base: Url) -> Self {
+        RestaurantsRequest { base }
+    }
+}
+
+#[async_trait]
+impl Downloadable for RestaurantsRequest {
+    fn url(&self) -> String {
+        format!("{}Menus", self.base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::default_base_url;
+
+    #[test]
+    fn test_restaurants_request_url() {
+        assert_eq!(
+            RestaurantsRequest::new(default_base_url()).url(),
+            "http://menu.dining.ucla.edu/Menus"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{Error, Result};
+use async_trait::async_trait;
+use reqwest::header::{self, HeaderMap};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+pub const NOT_MODIFIED: u16 = 304;
+
+/// A successful or not modified response, with the headers an HTTP cache needs
+#[derive(Debug, Clone, Default)]
+pub struct Response {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// Fetches the body of a page, abstracting over how the request is actually made
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn get(&self, url: &str) -> Result<String>;
+
+    /// Fetch a page sending extra request headers, such as the validators of a conditional
+    /// request. Answers other than success and 304 Not Modified are errors.
+    ///
+    /// Transports that cannot send headers fetch the page with `get` instead.
+    async fn fetch(&self, url: &str, _headers: HeaderMap) -> Result<Response> {
+        Ok(Response {
+            status: 200,
+            headers: HeaderMap::new(),
+            body: self.get(url).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    async fn get(&self, url: &str) -> Result<String> {
+        (**self).get(url).await
+    }
+
+    async fn fetch(&self, url: &str, headers: HeaderMap) -> Result<Response> {
+        (**self).fetch(url, headers).await
+    }
+}
+
+/// Downloads pages over the network with a reqwest client
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Use a client configured with custom TLS, proxy or default headers
+    pub fn with_client(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn get(&self, url: &str) -> Result<String> {
+        Ok(self.fetch(url, HeaderMap::new()).await?.body)
+    }
+
+    async fn fetch(&self, url: &str, headers: HeaderMap) -> Result<Response> {
+        let response = self
+            .client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| Error::network(url, e))?;
+        let status = response.status().as_u16();
+        if !response.status().is_success() && status != NOT_MODIFIED {
+            return Err(Error::status(url, status));
+        }
+        let headers = response.headers().clone();
+        let body = response.text().await.map_err(|e| Error::network(url, e))?;
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// Serves pages from memory, for tests and fixtures. Unknown urls answer with status 404.
+///
+/// Requests carrying the ETag of a page in `If-None-Match` answer with 304 Not Modified.
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    pages: HashMap<String, std::result::Result<String, u16>>,
+    headers: HashMap<String, HeaderMap>,
+    requested: Mutex<Vec<String>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn insert(&mut self, url: String, body: String) {
+        self.pages.insert(url, Ok(body));
+    }
+
+    /// Serve the page along with response headers, such as Cache-Control or ETag
+    pub fn insert_with_headers(&mut self, url: String, body: String, headers: HeaderMap) {
+        self.headers.insert(url.clone(), headers);
+        self.insert(url, body);
+    }
+
+    /// Answer requests to the url with the given HTTP status instead of a body
+    pub fn insert_status(&mut self, url: String, status: u16) {
+        self.pages.insert(url, Err(status));
+    }
+
+    /// All urls requested so far, in request order
+    pub fn requested(&self) -> Vec<String> {
+        self.requested.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn get(&self, url: &str) -> Result<String> {
+        Ok(self.fetch(url, HeaderMap::new()).await?.body)
+    }
+
+    async fn fetch(&self, url: &str, request: HeaderMap) -> Result<Response> {
+        self.requested.lock().unwrap().push(url.to_string());
+        let body = match self.pages.get(url) {
+            Some(Ok(body)) => body.clone(),
+            Some(Err(status)) => return Err(Error::status(url, *status)),
+            None => return Err(Error::status(url, 404)),
+        };
+        let headers = self.headers.get(url).cloned().unwrap_or_default();
+        let etag = headers.get(header::ETAG);
+        if etag.is_some() && etag == request.get(header::IF_NONE_MATCH) {
+            return Ok(Response {
+                status: NOT_MODIFIED,
+                headers,
+                body: String::new(),
+            });
+        }
+        Ok(Response {
+            status: 200,
+            headers,
+            body,
+        })
+    }
+}